@@ -1,24 +1,53 @@
-use html2text::render::text_renderer::TextDecorator;
+use html2text::render::text_renderer::{TaggedLineElement, TextDecorator};
+use std::borrow::Cow;
 
 #[derive(Clone)]
 pub struct MdDecorator {
     currentlink: String,
+    gfm: bool,
 }
 
 impl MdDecorator {
     pub fn new() -> MdDecorator {
         MdDecorator {
             currentlink: "".into(),
+            gfm: false,
+        }
+    }
+
+    /// Markdown for GitHub, whose parser only accepts delimiters with the right text around
+    /// them. The output needs to be passed through `finish_gfm`.
+    pub fn gfm() -> MdDecorator {
+        MdDecorator {
+            currentlink: "".into(),
+            gfm: true,
+        }
+    }
+
+    fn span(&self, span: GfmSpan, open: bool, markdown: &str) -> String {
+        match (self.gfm, open) {
+            (true, true) => span.open_mark().into(),
+            (true, false) => span.close_mark().into(),
+            (false, _) => markdown.into(),
         }
     }
 }
 
+/// Marks the text of `<pre>` blocks, so the decorators that need it can find them with
+/// `render_lines`, e.g. to put them in code fences.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Annotation {
+    #[default]
+    Text,
+    Preformat,
+}
+
 impl TextDecorator for MdDecorator {
-    type Annotation = ();
+    type Annotation = Annotation;
 
     fn decorate_link_start(&mut self, _url: &str) -> (String, Self::Annotation) {
         self.currentlink = _url.into();
-        ("[".into(), ())
+        ("[".into(), Annotation::Text)
     }
 
     fn decorate_link_end(&mut self) -> String {
@@ -26,42 +55,47 @@ impl TextDecorator for MdDecorator {
     }
 
     fn decorate_em_start(&self) -> (String, Self::Annotation) {
-        ("*".into(), ())
+        (self.span(GfmSpan::Em, true, "*"), Annotation::Text)
     }
 
     fn decorate_em_end(&self) -> String {
-        "*".into()
+        self.span(GfmSpan::Em, false, "*")
     }
 
     fn decorate_strong_start(&self) -> (String, Self::Annotation) {
-        ("**".into(), ())
+        (self.span(GfmSpan::Strong, true, "**"), Annotation::Text)
     }
 
     fn decorate_strong_end(&self) -> String {
-        "**".into()
+        self.span(GfmSpan::Strong, false, "**")
     }
 
     fn decorate_strikeout_start(&self) -> (String, Self::Annotation) {
-        ("~~".into(), ())
+        (self.span(GfmSpan::Strikeout, true, "~~"), Annotation::Text)
     }
 
     fn decorate_strikeout_end(&self) -> String {
-        "~~".into()
+        self.span(GfmSpan::Strikeout, false, "~~")
     }
 
     fn decorate_code_start(&self) -> (String, Self::Annotation) {
-        ("`".into(), ())
+        (self.span(GfmSpan::Code, true, "`"), Annotation::Text)
     }
 
     fn decorate_code_end(&self) -> String {
-        "`".into()
+        self.span(GfmSpan::Code, false, "`")
     }
 
-    fn decorate_preformat_first(&self) -> Self::Annotation {}
-    fn decorate_preformat_cont(&self) -> Self::Annotation {}
+    fn decorate_preformat_first(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    fn decorate_preformat_cont(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
 
     fn decorate_image(&mut self, src: &str, title: &str) -> (String, Self::Annotation) {
-        (format!("[{}]({})", title, src), ())
+        (format!("[{}]({})", title, src), Annotation::Text)
     }
 
     fn header_prefix(&self, level: usize) -> String {
@@ -73,7 +107,12 @@ impl TextDecorator for MdDecorator {
     }
 
     fn unordered_item_prefix(&self) -> String {
-        "* ".into()
+        // finish_gfm escapes asterisks in the text, which would include this one.
+        if self.gfm {
+            "- ".into()
+        } else {
+            "* ".into()
+        }
     }
 
     fn ordered_item_prefix(&self, i: i64) -> String {
@@ -83,7 +122,7 @@ impl TextDecorator for MdDecorator {
     fn finalise(
         &mut self,
         _links: Vec<String>,
-    ) -> Vec<html2text::render::text_renderer::TaggedLine<()>> {
+    ) -> Vec<html2text::render::text_renderer::TaggedLine<Annotation>> {
         Vec::new()
     }
 
@@ -92,6 +131,234 @@ impl TextDecorator for MdDecorator {
     }
 }
 
+/// Marks the code fences `render_markdown` writes for `MdDecorator::gfm`, so `finish_gfm`
+/// doesn't escape them or the code in them.
+const GFM_FENCE_MARK: char = '\u{E008}';
+
+/// Renders HTML with a decorator that annotates `<pre>` blocks, returning each line with the
+/// position its preformatted text starts at, if it has any.
+fn render_lines<D: TextDecorator<Annotation = Annotation>>(
+    html: &[u8],
+    width: usize,
+    decorator: D,
+) -> Vec<(String, Option<usize>)> {
+    let lines = html2text::config::with_decorator(decorator)
+        .lines_from_read(html, width)
+        .expect("Failed to convert to HTML");
+    lines
+        .into_iter()
+        .map(|line| {
+            let mut pre = None;
+            let mut position = 0;
+            for element in line.iter() {
+                if let TaggedLineElement::Str(x) = element {
+                    if x.tag.contains(&Annotation::Preformat) {
+                        pre = Some(position);
+                        break;
+                    }
+                    position += x.s.len();
+                }
+            }
+            (line.into_string(), pre)
+        })
+        .collect()
+}
+
+/// Renders HTML with `MdDecorator`, putting the lines of `<pre>` blocks in code fences. Fences
+/// in quotes and lists start with the same prefix as the first line of the block.
+pub fn render_markdown(html: &[u8], width: usize, decorator: MdDecorator) -> String {
+    let fence = if decorator.gfm {
+        GFM_FENCE_MARK.to_string()
+    } else {
+        "```".into()
+    };
+    let mut output = String::new();
+    // The prefix of the open fence, if any.
+    let mut fenced: Option<String> = None;
+    // Blank lines after a fenced line, which could be in the block or after it.
+    let mut blank: Vec<String> = Vec::new();
+    for (text, pre) in render_lines(html, width, decorator) {
+        if fenced.is_some() && pre.is_none() && text.trim_start_matches(['>', ' ']).is_empty() {
+            blank.push(text);
+            continue;
+        }
+        match (&fenced, pre) {
+            (None, Some(i)) => {
+                output += &format!("{}{}\n", &text[..i], fence);
+                fenced = Some(text[..i].into());
+            }
+            (Some(prefix), None) => {
+                output += &format!("{}{}\n", prefix, fence);
+                fenced = None;
+            }
+            _ => {}
+        }
+        for line in blank.drain(..).chain(std::iter::once(text)) {
+            output += &line;
+            output.push('\n');
+        }
+    }
+    if let Some(prefix) = fenced {
+        output += &format!("{}{}\n", prefix, fence);
+    }
+    for line in blank {
+        output += &line;
+        output.push('\n');
+    }
+    output
+}
+
+/// Inline spans `MdDecorator::gfm` marks with private use characters, which don't occur in the
+/// feed, so that `finish_gfm` can pick their delimiters once it knows the surrounding text.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GfmSpan {
+    Em,
+    Strong,
+    Strikeout,
+    Code,
+}
+
+impl GfmSpan {
+    const ALL: [GfmSpan; 4] = [
+        GfmSpan::Em,
+        GfmSpan::Strong,
+        GfmSpan::Strikeout,
+        GfmSpan::Code,
+    ];
+
+    fn open_mark(self) -> char {
+        match self {
+            GfmSpan::Em => '\u{E000}',
+            GfmSpan::Strong => '\u{E001}',
+            GfmSpan::Strikeout => '\u{E002}',
+            GfmSpan::Code => '\u{E003}',
+        }
+    }
+
+    fn close_mark(self) -> char {
+        match self {
+            GfmSpan::Em => '\u{E004}',
+            GfmSpan::Strong => '\u{E005}',
+            GfmSpan::Strikeout => '\u{E006}',
+            GfmSpan::Code => '\u{E007}',
+        }
+    }
+
+    fn is_mark(c: char) -> bool {
+        GfmSpan::ALL
+            .iter()
+            .any(|x| x.open_mark() == c || x.close_mark() == c)
+    }
+}
+
+/// Punctuation as far as GitHub's delimiter rules go, which includes symbols.
+fn is_gfm_punctuation(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+/// Renders a span whose content has already been finished. Whitespace just inside the span is
+/// moved outside, and spans GitHub wouldn't parse with delimiters, such as `**"a"**b`, fall back
+/// to HTML tags.
+fn render_gfm_span(
+    span: GfmSpan,
+    content: &str,
+    before: Option<char>,
+    after: Option<char>,
+) -> String {
+    if span == GfmSpan::Code {
+        if content.is_empty() {
+            return "".into();
+        }
+        let longest = content
+            .split(|x| x != '`')
+            .map(|x| x.len())
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest + 1);
+        let pad = if content.starts_with('`') || content.ends_with('`') {
+            " "
+        } else {
+            ""
+        };
+        return format!("{}{}{}{}{}", fence, pad, content, pad, fence);
+    }
+    // html2text strikes text out with combining characters, which the delimiters replace.
+    let content = if span == GfmSpan::Strikeout {
+        Cow::from(content.replace('\u{336}', ""))
+    } else {
+        Cow::from(content)
+    };
+    let core = content.trim();
+    let (Some(first), Some(last)) = (core.chars().next(), core.chars().next_back()) else {
+        return content.into_owned();
+    };
+    let lead = &content[..content.len() - content.trim_start().len()];
+    let trail = &content[content.trim_end().len()..];
+    let before = lead.chars().next_back().or(before);
+    let after = trail.chars().next().or(after);
+    let boundary = |x: Option<char>| match x {
+        Some(x) => x.is_whitespace() || is_gfm_punctuation(x),
+        None => true,
+    };
+    // A delimiter with punctuation on the inside only counts with whitespace or punctuation on
+    // the outside.
+    let flanking = (!is_gfm_punctuation(first) || boundary(before))
+        && (!is_gfm_punctuation(last) || boundary(after));
+    let (open, close) = match (span, flanking) {
+        (GfmSpan::Em, true) => ("*", "*"),
+        (GfmSpan::Strong, true) => ("**", "**"),
+        (GfmSpan::Strikeout, true) => ("~~", "~~"),
+        (GfmSpan::Em, false) => ("<em>", "</em>"),
+        (GfmSpan::Strong, false) => ("<strong>", "</strong>"),
+        (GfmSpan::Strikeout, false) => ("<del>", "</del>"),
+        (GfmSpan::Code, _) => unreachable!(),
+    };
+    format!("{}{}{}{}{}", lead, open, core, close, trail)
+}
+
+/// Replaces the span markers from `MdDecorator::gfm` with delimiters GitHub parses, and escapes
+/// the `*`, `~` and `` ` `` in the text so they aren't taken for delimiters.
+pub fn finish_gfm(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::new();
+    let mut open: Vec<(GfmSpan, usize)> = Vec::new();
+    let mut fenced = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if c == GFM_FENCE_MARK {
+            output += "```";
+            fenced = !fenced;
+        } else if fenced {
+            // Code blocks have no spans, so only their text is kept.
+            if !GfmSpan::is_mark(c) {
+                output.push(c);
+            }
+        } else if let Some(span) = GfmSpan::ALL.into_iter().find(|x| x.open_mark() == c) {
+            open.push((span, output.len()));
+        } else if let Some(span) = GfmSpan::ALL.into_iter().find(|x| x.close_mark() == c) {
+            let Some(position) = open.iter().rposition(|(x, _)| *x == span) else {
+                continue;
+            };
+            let start = open[position].1;
+            open.truncate(position);
+            let content = output.split_off(start);
+            // Neighbouring spans end up as delimiters or tags, both punctuation.
+            let before = output.chars().next_back();
+            let after = chars
+                .get(i + 1)
+                .map(|&x| if GfmSpan::is_mark(x) { '*' } else { x });
+            output += &render_gfm_span(span, &content, before, after);
+        } else if open.iter().any(|(x, _)| *x == GfmSpan::Code) {
+            output.push(c);
+        } else {
+            if matches!(c, '*' | '~' | '`') {
+                output.push('\\');
+            }
+            output.push(c);
+        }
+    }
+    output
+}
+
 #[derive(Clone)]
 pub struct PlainDecorator {
     currentlink: String,
@@ -183,3 +450,936 @@ impl TextDecorator for PlainDecorator {
         self.clone()
     }
 }
+
+/// The tags `Blocks` writes for a markup language, and the marks its decorator writes at the
+/// start of lines for them.
+#[cfg(any(feature = "decorator-bbcode", feature = "decorator-html"))]
+struct BlockTags {
+    quote_mark: &'static str,
+    ul_mark: &'static str,
+    ol_mark: &'static str,
+    /// Written before and after the level of a header.
+    header_mark: char,
+    quote: (&'static str, &'static str),
+    /// Unordered and ordered lists, in that order.
+    lists: [(&'static str, &'static str); 2],
+    item: (&'static str, &'static str),
+    pre: (&'static str, &'static str),
+    /// Tags for text outside of lists, or `None` to keep the lines as they are, with their
+    /// indentation and the blank lines between them.
+    paragraph: Option<(&'static str, &'static str)>,
+    /// Writes a header from its level and title.
+    header: fn(&str, &str) -> String,
+}
+
+/// The blocks open while going through the lines of a decorator that marks them at the start of
+/// lines.
+///
+/// html2text has no hook for the start and end of a quote or list, so they're found from the
+/// prefixes and indentation of the lines. Preformatted lines are kept as they are.
+#[cfg(any(feature = "decorator-bbcode", feature = "decorator-html"))]
+struct Blocks {
+    tags: &'static BlockTags,
+    lines: Vec<String>,
+    /// Indentation of each open list and whether it's ordered, innermost last. Each has an open
+    /// item.
+    lists: Vec<(usize, bool)>,
+    quotes: usize,
+    paragraph: bool,
+    pre: bool,
+    /// Blank lines after a preformatted line, which could be in the block or after it.
+    blank: usize,
+}
+
+#[cfg(any(feature = "decorator-bbcode", feature = "decorator-html"))]
+impl Blocks {
+    fn new(tags: &'static BlockTags) -> Blocks {
+        Blocks {
+            tags,
+            lines: Vec::new(),
+            lists: Vec::new(),
+            quotes: 0,
+            paragraph: false,
+            pre: false,
+            blank: 0,
+        }
+    }
+
+    /// Indentation to write before tags, which is only kept without paragraphs.
+    fn indent(&self, indent: usize) -> String {
+        match self.tags.paragraph {
+            Some(_) => String::new(),
+            None => " ".repeat(indent),
+        }
+    }
+
+    /// Adds the closing tag of a block, keeping the blank lines after the block outside of it.
+    fn close(&mut self, tag: String) {
+        let end = self
+            .lines
+            .iter()
+            .rposition(|x| !x.trim().is_empty())
+            .map_or(0, |x| x + 1);
+        self.lines.insert(end, tag);
+    }
+
+    /// Adds a closing tag to the end of the last line.
+    fn close_inline(&mut self, tag: &str) {
+        if let Some(last) = self.lines.last_mut() {
+            last.push_str(tag);
+        }
+    }
+
+    fn close_paragraph(&mut self) {
+        if self.paragraph
+            && let Some((_, close)) = self.tags.paragraph
+        {
+            self.close_inline(close);
+            self.paragraph = false;
+        }
+    }
+
+    fn close_pre(&mut self) {
+        if self.pre {
+            self.close_inline(self.tags.pre.1);
+            // The blank lines after the block, which paragraphs don't keep.
+            if self.tags.paragraph.is_none() {
+                self.lines
+                    .extend(std::iter::repeat_n(String::new(), self.blank));
+            }
+            self.pre = false;
+        }
+        self.blank = 0;
+    }
+
+    /// Closes the lists indented at least as far as `indent`.
+    fn close_lists(&mut self, indent: usize) {
+        while let Some(&(list, ordered)) = self.lists.last()
+            && list >= indent
+        {
+            self.close_inline(self.tags.item.1);
+            let tag = self.tags.lists[ordered as usize].1;
+            self.close(format!("{}{}", self.indent(list), tag));
+            self.lists.pop();
+        }
+    }
+
+    fn set_quotes(&mut self, quotes: usize) {
+        if quotes != self.quotes {
+            self.close_paragraph();
+            self.close_pre();
+            self.close_lists(0);
+        }
+        while self.quotes < quotes {
+            self.lines.push(self.tags.quote.0.into());
+            self.quotes += 1;
+        }
+        while self.quotes > quotes {
+            self.close(self.tags.quote.1.into());
+            self.quotes -= 1;
+        }
+    }
+
+    /// Adds a line of the decorator's output, with the position its preformatted text starts at
+    /// if it has any.
+    fn push(&mut self, line: &str, pre: Option<usize>) {
+        let tags = self.tags;
+        let (mut rest, code) = match pre {
+            Some(i) => (&line[..i], Some(&line[i..])),
+            None => (line, None),
+        };
+        let mut quotes = 0;
+        // Blank lines in quotes lose the space after the mark.
+        while let Some(x) = rest
+            .strip_prefix(tags.quote_mark)
+            .or_else(|| rest.strip_prefix(tags.quote_mark.trim_end()))
+        {
+            rest = x;
+            quotes += 1;
+        }
+        let content = rest.trim_start();
+        let indent = rest.len() - content.len();
+        if let Some(code) = code {
+            self.set_quotes(quotes);
+            self.close_paragraph();
+            self.close_lists(indent);
+            if self.pre {
+                let blank = std::mem::take(&mut self.blank);
+                self.lines.extend(std::iter::repeat_n(String::new(), blank));
+                self.lines.push(code.into());
+            } else {
+                let line = format!("{}{}{}", self.indent(indent), tags.pre.0, code);
+                self.lines.push(line);
+                self.pre = true;
+            }
+            return;
+        }
+        if content.is_empty() {
+            self.close_paragraph();
+            if self.pre {
+                self.blank += 1;
+            } else if tags.paragraph.is_none() {
+                self.lines.push(String::new());
+            }
+            return;
+        }
+        self.set_quotes(quotes);
+        self.close_pre();
+        let item = match content.strip_prefix(tags.ul_mark) {
+            Some(text) => Some((false, text)),
+            None => content.strip_prefix(tags.ol_mark).map(|text| (true, text)),
+        };
+        if let Some((ordered, text)) = item {
+            self.close_paragraph();
+            self.close_lists(indent + 1);
+            match self.lists.last() {
+                Some(&(list, kind)) if list == indent && kind == ordered => {
+                    self.close_inline(tags.item.1);
+                }
+                Some(&(list, _)) if list == indent => {
+                    self.close_lists(indent);
+                    self.open_list(indent, ordered);
+                }
+                _ => self.open_list(indent, ordered),
+            }
+            let line = format!("{}{}{}", self.indent(indent), tags.item.0, text);
+            self.lines.push(line);
+        } else if let Some(header) = content.strip_prefix(tags.header_mark)
+            && let Some((level, title)) = header.split_once(tags.header_mark)
+        {
+            self.close_paragraph();
+            self.close_lists(indent);
+            let line = format!("{}{}", self.indent(indent), (tags.header)(level, title));
+            self.lines.push(line);
+        } else {
+            self.close_lists(indent);
+            match tags.paragraph {
+                None => self.lines.push(rest.into()),
+                // Continues the open list item or paragraph.
+                Some(_) if !self.lists.is_empty() || self.paragraph => {
+                    self.lines.push(content.into())
+                }
+                Some((open, _)) => {
+                    self.lines.push(format!("{}{}", open, content));
+                    self.paragraph = true;
+                }
+            }
+        }
+    }
+
+    fn open_list(&mut self, indent: usize, ordered: bool) {
+        let tag = self.tags.lists[ordered as usize].0;
+        let line = format!("{}{}", self.indent(indent), tag);
+        self.lines.push(line);
+        self.lists.push((indent, ordered));
+    }
+
+    /// Closes the blocks left open, returning the lines.
+    fn finish(mut self) -> Vec<String> {
+        self.close_paragraph();
+        self.close_pre();
+        self.close_lists(0);
+        self.set_quotes(0);
+        self.lines
+    }
+}
+
+/// Marks `BBCodeDecorator` writes at the start of lines for `render_bbcode`, which turns them
+/// into the tags for their blocks. Like in `html_mark`, the prefixes of quotes and list items
+/// are ASCII.
+#[cfg(feature = "decorator-bbcode")]
+mod bbcode_mark {
+    pub const HEADER: char = '\u{E015}';
+    pub const QUOTE: &str = "[quote] ";
+    pub const UL: &str = "[*]";
+    pub const OL: &str = "[#]";
+}
+
+#[cfg(feature = "decorator-bbcode")]
+#[derive(Clone)]
+pub struct BBCodeDecorator;
+
+#[cfg(feature = "decorator-bbcode")]
+impl BBCodeDecorator {
+    pub fn new() -> BBCodeDecorator {
+        BBCodeDecorator
+    }
+}
+
+#[cfg(feature = "decorator-bbcode")]
+impl TextDecorator for BBCodeDecorator {
+    type Annotation = Annotation;
+
+    fn decorate_link_start(&mut self, url: &str) -> (String, Self::Annotation) {
+        (format!("[url={}]", url), Annotation::Text)
+    }
+
+    fn decorate_link_end(&mut self) -> String {
+        "[/url]".into()
+    }
+
+    fn decorate_em_start(&self) -> (String, Self::Annotation) {
+        ("[i]".into(), Annotation::Text)
+    }
+
+    fn decorate_em_end(&self) -> String {
+        "[/i]".into()
+    }
+
+    fn decorate_strong_start(&self) -> (String, Self::Annotation) {
+        ("[b]".into(), Annotation::Text)
+    }
+
+    fn decorate_strong_end(&self) -> String {
+        "[/b]".into()
+    }
+
+    fn decorate_strikeout_start(&self) -> (String, Self::Annotation) {
+        ("[s]".into(), Annotation::Text)
+    }
+
+    fn decorate_strikeout_end(&self) -> String {
+        "[/s]".into()
+    }
+
+    fn decorate_code_start(&self) -> (String, Self::Annotation) {
+        ("[code]".into(), Annotation::Text)
+    }
+
+    fn decorate_code_end(&self) -> String {
+        "[/code]".into()
+    }
+
+    fn decorate_preformat_first(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    fn decorate_preformat_cont(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    fn decorate_image(&mut self, src: &str, _title: &str) -> (String, Self::Annotation) {
+        (format!("[img]{}[/img]", src), Annotation::Text)
+    }
+
+    fn header_prefix(&self, level: usize) -> String {
+        format!("{}{}{}", bbcode_mark::HEADER, level, bbcode_mark::HEADER)
+    }
+
+    fn quote_prefix(&self) -> String {
+        bbcode_mark::QUOTE.into()
+    }
+
+    fn unordered_item_prefix(&self) -> String {
+        bbcode_mark::UL.into()
+    }
+
+    fn ordered_item_prefix(&self, _i: i64) -> String {
+        bbcode_mark::OL.into()
+    }
+
+    fn finalise(
+        &mut self,
+        _links: Vec<String>,
+    ) -> Vec<html2text::render::text_renderer::TaggedLine<Annotation>> {
+        Vec::new()
+    }
+
+    fn make_subblock_decorator(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// How `render_bbcode` writes blocks. Only the top two header levels are bigger, as forums
+/// scale sizes differently.
+#[cfg(feature = "decorator-bbcode")]
+static BBCODE_TAGS: BlockTags = BlockTags {
+    quote_mark: bbcode_mark::QUOTE,
+    ul_mark: bbcode_mark::UL,
+    ol_mark: bbcode_mark::OL,
+    header_mark: bbcode_mark::HEADER,
+    quote: ("[quote]", "[/quote]"),
+    lists: [("[list]", "[/list]"), ("[list=1]", "[/list]")],
+    item: ("[*]", ""),
+    pre: ("[code]", "[/code]"),
+    paragraph: None,
+    header: |level, title| match level {
+        "1" => format!("[size=150][b]{}[/b][/size]", title),
+        "2" => format!("[size=125][b]{}[/b][/size]", title),
+        _ => format!("[b]{}[/b]", title),
+    },
+};
+
+/// Renders HTML with `BBCodeDecorator`, turning the marks at the start of lines into tags with
+/// `Blocks`. Preformatted lines are kept as they are in `[code]` blocks.
+#[cfg(feature = "decorator-bbcode")]
+pub fn render_bbcode(html: &[u8], width: usize, decorator: BBCodeDecorator) -> String {
+    let mut blocks = Blocks::new(&BBCODE_TAGS);
+    for (line, pre) in render_lines(html, width, decorator) {
+        blocks.push(&line, pre);
+    }
+    blocks.finish().into_iter().map(|x| x + "\n").collect()
+}
+
+/// Marks header lines for `finish_rst`, as RST headers are underlined rather than prefixed.
+#[cfg(feature = "decorator-rst")]
+const RST_HEADER_MARK: char = '\u{1}';
+
+/// Underline characters for each header level.
+#[cfg(feature = "decorator-rst")]
+const RST_UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+#[cfg(feature = "decorator-rst")]
+#[derive(Clone)]
+pub struct RstDecorator {
+    currentlink: String,
+    // RST can't nest inline markup, so it's dropped inside links.
+    in_link: bool,
+}
+
+#[cfg(feature = "decorator-rst")]
+impl RstDecorator {
+    pub fn new() -> RstDecorator {
+        RstDecorator {
+            currentlink: "".into(),
+            in_link: false,
+        }
+    }
+
+    fn inline_markup(&self, markup: &str) -> String {
+        if self.in_link {
+            "".into()
+        } else {
+            markup.into()
+        }
+    }
+}
+
+#[cfg(feature = "decorator-rst")]
+impl TextDecorator for RstDecorator {
+    type Annotation = Annotation;
+
+    fn decorate_link_start(&mut self, _url: &str) -> (String, Self::Annotation) {
+        self.currentlink = _url.into();
+        self.in_link = true;
+        ("`".into(), Annotation::Text)
+    }
+
+    // Anonymous references, since release notes reuse link text like "here" for different
+    // URLs and named references with the same text conflict.
+    fn decorate_link_end(&mut self) -> String {
+        self.in_link = false;
+        format!(" <{}>`__", self.currentlink)
+    }
+
+    fn decorate_em_start(&self) -> (String, Self::Annotation) {
+        (self.inline_markup("*"), Annotation::Text)
+    }
+
+    fn decorate_em_end(&self) -> String {
+        self.inline_markup("*")
+    }
+
+    fn decorate_strong_start(&self) -> (String, Self::Annotation) {
+        (self.inline_markup("**"), Annotation::Text)
+    }
+
+    fn decorate_strong_end(&self) -> String {
+        self.inline_markup("**")
+    }
+
+    fn decorate_strikeout_start(&self) -> (String, Self::Annotation) {
+        ("".into(), Annotation::Text)
+    }
+
+    fn decorate_strikeout_end(&self) -> String {
+        "".into()
+    }
+
+    fn decorate_code_start(&self) -> (String, Self::Annotation) {
+        (self.inline_markup("``"), Annotation::Text)
+    }
+
+    fn decorate_code_end(&self) -> String {
+        self.inline_markup("``")
+    }
+
+    fn decorate_preformat_first(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    fn decorate_preformat_cont(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    fn decorate_image(&mut self, src: &str, title: &str) -> (String, Self::Annotation) {
+        (format!("`{} <{}>`__", title, src), Annotation::Text)
+    }
+
+    fn header_prefix(&self, level: usize) -> String {
+        format!("{}{}{}", RST_HEADER_MARK, level, RST_HEADER_MARK)
+    }
+
+    fn quote_prefix(&self) -> String {
+        "    ".into()
+    }
+
+    fn unordered_item_prefix(&self) -> String {
+        "- ".into()
+    }
+
+    fn ordered_item_prefix(&self, _i: i64) -> String {
+        "#. ".into()
+    }
+
+    fn finalise(
+        &mut self,
+        _links: Vec<String>,
+    ) -> Vec<html2text::render::text_renderer::TaggedLine<Annotation>> {
+        Vec::new()
+    }
+
+    fn make_subblock_decorator(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Renders HTML with `RstDecorator`, turning the header markers into underlines and the lines
+/// of `<pre>` blocks into literal blocks. RST needs a blank line wherever the indentation
+/// changes, e.g. before a nested list and before the next item of its parent list, so those
+/// are added too.
+#[cfg(feature = "decorator-rst")]
+pub fn render_rst(html: &[u8], width: usize, decorator: RstDecorator) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut last_indent: Option<usize> = None;
+    // The indentation of the open literal block, if any.
+    let mut literal: Option<usize> = None;
+    // Blank lines after a literal line, which could be in the block or after it.
+    let mut blank = 0;
+    for (raw, pre) in render_lines(html, width, decorator) {
+        let line = raw.trim_end();
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.is_empty() {
+            if literal.is_some() {
+                blank += 1;
+            } else {
+                lines.push("".into());
+            }
+            last_indent = None;
+            continue;
+        }
+        if let Some(i) = pre {
+            let (prefix, text) = raw.split_at(i);
+            match literal {
+                Some(_) => lines.extend(std::iter::repeat_n("".into(), blank)),
+                None => {
+                    if lines.last().is_some_and(|x| !x.is_empty()) {
+                        lines.push("".into());
+                    }
+                    lines.push(format!("{}::", prefix));
+                    lines.push("".into());
+                    literal = Some(prefix.chars().count() + 4);
+                }
+            }
+            blank = 0;
+            lines.push(format!(
+                "{}{}",
+                " ".repeat(literal.unwrap_or(0)),
+                text.trim_end()
+            ));
+            continue;
+        }
+        if literal.take().is_some() {
+            lines.push("".into());
+            blank = 0;
+        } else if last_indent.is_some_and(|x| indent != x) {
+            lines.push("".into());
+        }
+        last_indent = Some(indent);
+        if let Some(header) = trimmed.strip_prefix(RST_HEADER_MARK)
+            && let Some((level, title)) = header.split_once(RST_HEADER_MARK)
+        {
+            let level = level
+                .parse::<usize>()
+                .unwrap_or(1)
+                .clamp(1, RST_UNDERLINES.len());
+            let underline = RST_UNDERLINES[level - 1].to_string();
+            lines.push(format!("{}{}", &line[..indent], title));
+            lines.push(format!(
+                "{}{}",
+                &line[..indent],
+                underline.repeat(title.chars().count())
+            ));
+        } else {
+            lines.push(line.into());
+        }
+    }
+    lines.into_iter().map(|x| x + "\n").collect()
+}
+
+/// Marks `HtmlDecorator` writes markup with for `finish_html`. Tags are written with characters
+/// in the private use area so they can be told apart from text that needs escaping. html2text
+/// needs the prefixes of quotes and list items to be as wide as they are long, so those are
+/// ASCII like the `[*]` of `BBCodeDecorator`.
+#[cfg(feature = "decorator-html")]
+mod html_mark {
+    pub const LT: char = '\u{E010}';
+    pub const GT: char = '\u{E011}';
+    pub const QUOTE: char = '\u{E012}';
+    pub const HEADER: char = '\u{E013}';
+    pub const PRE: char = '\u{E014}';
+    pub const BLOCKQUOTE: &str = "[quote] ";
+    pub const UL: &str = "[ul] ";
+    pub const OL: &str = "[ol] ";
+}
+
+/// Writes an HTML tag with the marks from `html_mark`.
+#[cfg(feature = "decorator-html")]
+pub fn html_tag(tag: &str) -> String {
+    tag.replace('<', &html_mark::LT.to_string())
+        .replace('>', &html_mark::GT.to_string())
+        .replace('"', &html_mark::QUOTE.to_string())
+}
+
+#[cfg(feature = "decorator-html")]
+#[derive(Clone)]
+pub struct HtmlDecorator;
+
+#[cfg(feature = "decorator-html")]
+impl HtmlDecorator {
+    pub fn new() -> HtmlDecorator {
+        HtmlDecorator
+    }
+}
+
+#[cfg(feature = "decorator-html")]
+impl TextDecorator for HtmlDecorator {
+    type Annotation = Annotation;
+
+    fn decorate_link_start(&mut self, url: &str) -> (String, Self::Annotation) {
+        (
+            html_tag(&format!("<a href=\"{}\">", url.replace('"', "%22"))),
+            Annotation::Text,
+        )
+    }
+
+    fn decorate_link_end(&mut self) -> String {
+        html_tag("</a>")
+    }
+
+    fn decorate_em_start(&self) -> (String, Self::Annotation) {
+        (html_tag("<em>"), Annotation::Text)
+    }
+
+    fn decorate_em_end(&self) -> String {
+        html_tag("</em>")
+    }
+
+    fn decorate_strong_start(&self) -> (String, Self::Annotation) {
+        (html_tag("<strong>"), Annotation::Text)
+    }
+
+    fn decorate_strong_end(&self) -> String {
+        html_tag("</strong>")
+    }
+
+    fn decorate_strikeout_start(&self) -> (String, Self::Annotation) {
+        (html_tag("<del>"), Annotation::Text)
+    }
+
+    fn decorate_strikeout_end(&self) -> String {
+        html_tag("</del>")
+    }
+
+    fn decorate_code_start(&self) -> (String, Self::Annotation) {
+        (html_tag("<code>"), Annotation::Text)
+    }
+
+    fn decorate_code_end(&self) -> String {
+        html_tag("</code>")
+    }
+
+    fn decorate_preformat_first(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    fn decorate_preformat_cont(&self) -> Self::Annotation {
+        Annotation::Preformat
+    }
+
+    // The alt text is escaped by finish_html along with the rest of the text.
+    fn decorate_image(&mut self, src: &str, title: &str) -> (String, Self::Annotation) {
+        let src = html_tag(&format!("<img src=\"{}\" alt=\"", src.replace('"', "%22")));
+        (
+            format!("{}{}{}", src, title, html_tag("\">")),
+            Annotation::Text,
+        )
+    }
+
+    fn header_prefix(&self, level: usize) -> String {
+        format!(
+            "{}{}{}",
+            html_mark::HEADER,
+            level.clamp(1, 6),
+            html_mark::HEADER
+        )
+    }
+
+    fn quote_prefix(&self) -> String {
+        html_mark::BLOCKQUOTE.into()
+    }
+
+    fn unordered_item_prefix(&self) -> String {
+        html_mark::UL.into()
+    }
+
+    fn ordered_item_prefix(&self, _i: i64) -> String {
+        html_mark::OL.into()
+    }
+
+    fn finalise(
+        &mut self,
+        _links: Vec<String>,
+    ) -> Vec<html2text::render::text_renderer::TaggedLine<Annotation>> {
+        Vec::new()
+    }
+
+    fn make_subblock_decorator(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Renders HTML with `HtmlDecorator`, marking where the text of `<pre>` blocks starts on their
+/// lines. The output needs to be passed through `finish_html`.
+#[cfg(feature = "decorator-html")]
+pub fn render_html(html: &[u8], width: usize, decorator: HtmlDecorator) -> String {
+    let mut output = String::new();
+    for (mut text, pre) in render_lines(html, width, decorator) {
+        if let Some(i) = pre {
+            text.insert(i, html_mark::PRE);
+        }
+        output += &text;
+        output.push('\n');
+    }
+    output
+}
+
+/// How `finish_html` writes blocks.
+#[cfg(feature = "decorator-html")]
+static HTML_TAGS: BlockTags = BlockTags {
+    quote_mark: html_mark::BLOCKQUOTE,
+    ul_mark: html_mark::UL,
+    ol_mark: html_mark::OL,
+    header_mark: html_mark::HEADER,
+    quote: ("<blockquote>", "</blockquote>"),
+    lists: [("<ul>", "</ul>"), ("<ol>", "</ol>")],
+    item: ("<li>", "</li>"),
+    pre: ("<pre>", "</pre>"),
+    paragraph: Some(("<p>", "</p>")),
+    header: |level, title| format!("<h{}>{}</h{}>", level, title, level),
+};
+
+/// Turns the marks from `render_html` into tags with `Blocks` and escapes the text around them.
+/// Preformatted lines are kept as they are in `<pre>` blocks, and the other lines become
+/// paragraphs.
+#[cfg(feature = "decorator-html")]
+pub fn finish_html(text: &str) -> String {
+    let mut blocks = Blocks::new(&HTML_TAGS);
+    for line in text.split('\n') {
+        let mut line = line
+            .replace('\u{336}', "")
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace(html_mark::LT, "<")
+            .replace(html_mark::GT, ">")
+            .replace(html_mark::QUOTE, "\"");
+        let pre = line.find(html_mark::PRE);
+        if let Some(i) = pre {
+            line.remove(i);
+        }
+        blocks.push(&line, pre);
+    }
+    blocks.finish().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fences_preformatted_blocks_in_markdown() {
+        let html =
+            "<p>Run:</p><pre>sudo crossystem dev_boot_usb=1\n\n  echo done</pre><p>After.</p>";
+        assert_eq!(
+            render_markdown(html.as_bytes(), usize::MAX, MdDecorator::new()),
+            "Run:\n\n```\nsudo crossystem dev_boot_usb=1\n\n  echo done\n```\n\nAfter.\n"
+        );
+    }
+
+    #[test]
+    fn fences_preformatted_blocks_in_quotes() {
+        let html = "<blockquote><pre>a *b*</pre></blockquote>";
+        assert_eq!(
+            render_markdown(html.as_bytes(), usize::MAX, MdDecorator::new()),
+            "> ```\n> a *b*\n> ```\n"
+        );
+        assert_eq!(
+            finish_gfm(&render_markdown(
+                html.as_bytes(),
+                usize::MAX,
+                MdDecorator::gfm()
+            )),
+            "> ```\n> a *b*\n> ```\n"
+        );
+    }
+
+    #[cfg(feature = "decorator-rst")]
+    #[test]
+    fn separates_nested_lists_in_rst() {
+        let html = "<ul><li>One<ul><li>Nested</li><li>Nested two<ol><li>Deep</li></ol></li></ul>\
+            </li><li>Two</li></ul>";
+        assert_eq!(
+            render_rst(html.as_bytes(), usize::MAX, RstDecorator::new()),
+            "- One\n\n  - Nested\n  - Nested two\n\n    #. Deep\n\n- Two\n"
+        );
+    }
+
+    #[cfg(feature = "decorator-rst")]
+    #[test]
+    fn writes_preformatted_blocks_as_rst_literal_blocks() {
+        let html =
+            "<p>Run:</p><pre>sudo crossystem dev_boot_usb=1\n\n  echo done</pre><p>After.</p>";
+        assert_eq!(
+            render_rst(html.as_bytes(), usize::MAX, RstDecorator::new()),
+            "Run:\n\n::\n\n    sudo crossystem dev_boot_usb=1\n\n      echo done\n\nAfter.\n"
+        );
+        let html = "<ul><li>Item<pre>code</pre></li></ul>";
+        assert_eq!(
+            render_rst(html.as_bytes(), usize::MAX, RstDecorator::new()),
+            "- Item\n\n  ::\n\n      code\n"
+        );
+    }
+
+    #[cfg(feature = "decorator-html")]
+    #[test]
+    fn keeps_preformatted_blocks_in_html() {
+        let html = "<p>Run:</p><pre>if a &lt; b {\n\n    echo done\n}</pre><p>After.</p>";
+        assert_eq!(
+            finish_html(&render_html(
+                html.as_bytes(),
+                usize::MAX,
+                HtmlDecorator::new()
+            )),
+            "<p>Run:</p>\n<pre>if a &lt; b {\n\n    echo done\n}</pre>\n<p>After.</p>"
+        );
+        let html = "<ul><li>Item<pre>  code</pre></li></ul>";
+        assert_eq!(
+            finish_html(&render_html(
+                html.as_bytes(),
+                usize::MAX,
+                HtmlDecorator::new()
+            )),
+            "<ul>\n<li>Item\n<pre>  code</pre></li>\n</ul>"
+        );
+    }
+
+    #[cfg(feature = "decorator-html")]
+    #[test]
+    fn writes_html_blocks() {
+        let html = "<h2>Fixes</h2><blockquote><p>Quoted</p></blockquote>\
+            <ol><li>One</li><li>Two<ul><li>Nested</li></ul></li></ol><p>After</p>";
+        assert_eq!(
+            finish_html(&render_html(
+                html.as_bytes(),
+                usize::MAX,
+                HtmlDecorator::new()
+            )),
+            "<h2>Fixes</h2>\n<blockquote>\n<p>Quoted</p>\n</blockquote>\n<ol>\n<li>One</li>\n\
+             <li>Two\n<ul>\n<li>Nested</li>\n</ul></li>\n</ol>\n<p>After</p>"
+        );
+    }
+
+    #[cfg(feature = "decorator-bbcode")]
+    #[test]
+    fn writes_bbcode_blocks() {
+        let html = "<h2>Fixes</h2><blockquote><p>Quoted</p></blockquote>\
+            <ol><li>One</li><li>Two<ul><li>Nested</li></ul></li></ol><pre>  code</pre>";
+        assert_eq!(
+            render_bbcode(html.as_bytes(), usize::MAX, BBCodeDecorator::new()),
+            "[size=125][b]Fixes[/b][/size]\n\n[quote]\nQuoted\n[/quote]\n\n[list=1]\n[*]One\n\
+             [*]Two\n\n   [list]\n   [*]Nested\n   [/list]\n[/list]\n[code]  code[/code]\n"
+        );
+    }
+
+    /// Renders HTML with `MdDecorator::gfm` and then renders the markdown back to HTML like
+    /// GitHub does.
+    fn gfm_round_trip(html: &str) -> (String, String) {
+        let markdown = finish_gfm(&render_markdown(
+            html.as_bytes(),
+            usize::MAX,
+            MdDecorator::gfm(),
+        ));
+        let mut options = comrak::Options::default();
+        options.extension.strikethrough = true;
+        options.render.r#unsafe = true;
+        let rendered = comrak::markdown_to_html(&markdown, &options);
+        (markdown.trim_end().into(), rendered.trim_end().into())
+    }
+
+    #[test]
+    fn renders_gfm_fixtures_as_github_does() {
+        let fixtures = [
+            (
+                "<p><strong>Bold</strong>text and <em>em</em>phasis</p>",
+                "**Bold**text and *em*phasis",
+                "<p><strong>Bold</strong>text and <em>em</em>phasis</p>",
+            ),
+            (
+                "<p>a<strong> spaced </strong>b</p>",
+                "a **spaced** b",
+                "<p>a <strong>spaced</strong> b</p>",
+            ),
+            (
+                "<p><strong>\"Quoted\"</strong>after</p>",
+                "<strong>\"Quoted\"</strong>after",
+                "<p><strong>&quot;Quoted&quot;</strong>after</p>",
+            ),
+            (
+                "<p>Was <del>$10</del> now free</p>",
+                "Was ~~$10~~ now free",
+                "<p>Was <del>$10</del> now free</p>",
+            ),
+            (
+                "<p><del>Removed</del>, fixed</p>",
+                "~~Removed~~, fixed",
+                "<p><del>Removed</del>, fixed</p>",
+            ),
+            (
+                "<p>2 * 3 ~~ 4 and a `tick`</p>",
+                "2 \\* 3 \\~\\~ 4 and a \\`tick\\`",
+                "<p>2 * 3 ~~ 4 and a `tick`</p>",
+            ),
+            (
+                "<p>Run <code>a`b</code> now</p>",
+                "Run ``a`b`` now",
+                "<p>Run <code>a`b</code> now</p>",
+            ),
+            (
+                "<p><em><strong>Both</strong></em></p>",
+                "***Both***",
+                "<p><em><strong>Both</strong></em></p>",
+            ),
+        ];
+        for (html, markdown, rendered) in fixtures {
+            assert_eq!(
+                gfm_round_trip(html),
+                (markdown.into(), rendered.into()),
+                "{}",
+                html
+            );
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use clap::ValueEnum;
+
+/// Language to show notifications in.
+#[derive(ValueEnum, Debug, Copy, Clone)]
+pub enum Lang {
+    En,
+    De,
+    Es,
+    Fr,
+}
+
+/// Notification text in one language. `{date}` is replaced with the release date, formatted
+/// with `date_format`.
+#[cfg(feature = "desktop")]
+pub struct Strings {
+    pub release: &'static str,
+    pub rerelease: &'static str,
+    pub batch: &'static str,
+    pub date_format: &'static str,
+}
+
+#[cfg(feature = "desktop")]
+impl Lang {
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Lang::En => &Strings {
+                release: "ChromeOS Release on {date}",
+                rerelease: "ChromeOS Re-release on {date}",
+                batch: "ChromeOS Releases",
+                date_format: "%Y/%m/%d",
+            },
+            Lang::De => &Strings {
+                release: "ChromeOS-Update vom {date}",
+                rerelease: "Erneutes ChromeOS-Update vom {date}",
+                batch: "ChromeOS-Updates",
+                date_format: "%d.%m.%Y",
+            },
+            Lang::Es => &Strings {
+                release: "Versión de ChromeOS del {date}",
+                rerelease: "Versión de ChromeOS republicada el {date}",
+                batch: "Versiones de ChromeOS",
+                date_format: "%d/%m/%Y",
+            },
+            Lang::Fr => &Strings {
+                release: "Version de ChromeOS du {date}",
+                rerelease: "Version de ChromeOS republiée le {date}",
+                batch: "Versions de ChromeOS",
+                date_format: "%d/%m/%Y",
+            },
+        }
+    }
+}
@@ -1,51 +1,324 @@
 #![feature(let_chains)]
 mod decorators;
+mod i18n;
+mod timezone;
+mod version;
 
 use decorators::*;
+use i18n::*;
+use timezone::Timezone;
+use version::ChromeVersion;
 
-use bytes::Buf;
 use chrono::{DateTime, Utc};
-use clap::{Parser, ValueEnum};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
 
 #[derive(ValueEnum, Debug, Copy, Clone)]
 enum Decorator {
     Markdown,
     Plain,
+    #[cfg(feature = "decorator-bbcode")]
+    #[value(name = "bbcode")]
+    BBCode,
+    #[cfg(feature = "decorator-rst")]
+    Rst,
+    #[cfg(feature = "decorator-html")]
+    Html,
 }
 
 #[derive(ValueEnum, Debug, Copy, Clone)]
 enum OutputFormat {
     Json,
+    Yaml,
     Pretty,
+    /// A markdown document to paste into a GitHub release.
+    GithubRelease,
+    /// An RSS 2.0 feed.
+    Rss,
+    /// Each release rendered with --template.
+    Template,
+    /// Embeds posted to --discord-webhook.
+    Discord,
+    #[cfg(feature = "desktop")]
     Notification,
 }
 
+impl OutputFormat {
+    /// The decorator releases are formatted with for this format when --decorator isn't given.
+    fn default_decorator(self) -> Decorator {
+        match self {
+            OutputFormat::Json
+            | OutputFormat::Yaml
+            | OutputFormat::Pretty
+            | OutputFormat::GithubRelease
+            | OutputFormat::Template
+            | OutputFormat::Discord => Decorator::Markdown,
+            // RSS descriptions are shown as text.
+            OutputFormat::Rss => Decorator::Plain,
+            // Notifications are shown as plain text, where markdown symbols would show as is.
+            #[cfg(feature = "desktop")]
+            OutputFormat::Notification => Decorator::Plain,
+        }
+    }
+
+    /// How long the content of a release can be in this format, for formats whose platform
+    /// limits it. Notifications and Discord embeds show the summary instead, which is limited the
+    /// same way.
+    fn max_content_length(self) -> Option<usize> {
+        match self {
+            OutputFormat::Json
+            | OutputFormat::Yaml
+            | OutputFormat::Pretty
+            | OutputFormat::GithubRelease
+            | OutputFormat::Rss
+            | OutputFormat::Template => None,
+            OutputFormat::Discord => Some(4096),
+            #[cfg(feature = "desktop")]
+            OutputFormat::Notification => Some(256),
+        }
+    }
+
+    /// Whether printing releases in this format does more than write to stdout.
+    fn has_side_effects(self) -> bool {
+        match self {
+            OutputFormat::Json
+            | OutputFormat::Yaml
+            | OutputFormat::Pretty
+            | OutputFormat::GithubRelease
+            | OutputFormat::Rss
+            | OutputFormat::Template => false,
+            OutputFormat::Discord => true,
+            #[cfg(feature = "desktop")]
+            OutputFormat::Notification => true,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum UnderlineAs {
+    Strong,
+    Em,
+    /// Keep the `<u>` tags, as markup with the html decorator and as text with the others, for
+    /// formats that render HTML.
+    Html,
+    Ignore,
+}
+
+impl UnderlineAs {
+    /// The HTML to replace the opening and closing `<u>` tags with before converting.
+    fn tags(self) -> (&'static str, &'static str) {
+        match self {
+            UnderlineAs::Strong => ("<strong>", "</strong>"),
+            UnderlineAs::Em => ("<em>", "</em>"),
+            UnderlineAs::Html => ("&lt;u&gt;", "&lt;/u&gt;"),
+            UnderlineAs::Ignore => ("", ""),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum DiffBy {
+    Timestamp,
+    Id,
+    ContentHash,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum FirstRun {
+    /// Remember the releases as seen without outputting any of them.
+    MarkOnly,
+    /// Output every release, as they are all new.
+    Emit,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum GroupBy {
+    Milestone,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum SortOrder {
+    Date,
+    Relevance,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+enum Channel {
+    Stable,
+    Beta,
+    Dev,
+    Canary,
+    Lts,
+}
+
+impl Channel {
+    fn name(self) -> &'static str {
+        match self {
+            Channel::Stable => "Stable",
+            Channel::Beta => "Beta",
+            Channel::Dev => "Dev",
+            Channel::Canary => "Canary",
+            Channel::Lts => "LTS",
+        }
+    }
+
+    fn emoji(self) -> &'static str {
+        match self {
+            Channel::Stable => "🟢",
+            Channel::Beta => "🟡",
+            Channel::Dev => "🟠",
+            Channel::Canary => "🔴",
+            Channel::Lts => "🔵",
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone)]
+enum ChannelFilter {
+    All,
+    Stable,
+    Beta,
+    Dev,
+    Canary,
+    Lts,
+}
+
+impl ChannelFilter {
+    /// The channel to keep releases of, or `None` to keep all of them.
+    fn channel(self) -> Option<Channel> {
+        match self {
+            ChannelFilter::All => None,
+            ChannelFilter::Stable => Some(Channel::Stable),
+            ChannelFilter::Beta => Some(Channel::Beta),
+            ChannelFilter::Dev => Some(Channel::Dev),
+            ChannelFilter::Canary => Some(Channel::Canary),
+            ChannelFilter::Lts => Some(Channel::Lts),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Show the releases in a saved feed that aren't in an older one, matched by entry id.
+    Diff {
+        /// The older feed file.
+        old: PathBuf,
+        /// The newer feed file.
+        new: PathBuf,
+    },
+}
+
 /// ChromeOS Releases commandline.
 ///
 /// Fetches the Chrome Releases feed and filters to only chromeOS updates.
 #[derive(Parser)]
 #[command(version = clap::crate_version!())]
 struct Cli {
-    /// Decorator to format the release HTML with.
-    #[arg(short = 'D', long, value_enum, default_value_t = Decorator::Markdown)]
-    decorator: Decorator,
+    #[command(subcommand)]
+    command: Option<Commands>,
 
-    /// Format to print releases in.
-    #[arg(short, long, value_enum, default_value_t = OutputFormat::Pretty)]
-    format: OutputFormat,
+    /// Decorator to format the release HTML with. Defaults to the one for the first --format,
+    /// plain for notification and markdown for the others.
+    #[arg(short = 'D', long, value_enum, global = true)]
+    decorator: Option<Decorator>,
+
+    /// Format to print releases in. Can be given multiple times to output each format in turn,
+    /// e.g. to print and notify at once. --count-only uses the first format.
+    #[arg(short, long, value_enum, default_values_t = [OutputFormat::Pretty], global = true)]
+    format: Vec<OutputFormat>,
+
+    /// Width to wrap the content of the pretty format at, or 0 for the terminal's width. It isn't
+    /// wrapped by default, and the other formats are never wrapped.
+    #[arg(short, long, global = true)]
+    width: Option<usize>,
+
+    /// Make markdown follow GitHub's rules for where emphasis, strikethrough and code can start
+    /// and end, falling back to HTML tags where they can't, and escape literal `*`, `~` and `` ` ``.
+    /// Needs the markdown decorator.
+    #[arg(long, global = true)]
+    gfm: bool,
+
+    /// Indent the json format for reading. It's compact by default to keep it small.
+    #[arg(long, global = true)]
+    json_pretty: bool,
+
+    /// Template for the template format, e.g. `## {title}\n{timestamp:%Y-%m-%d}\n{content}\n`.
+    /// The fields are title, summary, content and timestamp, which takes a strftime format after
+    /// a colon. `\n` and `\t` are a newline and a tab, and `\\` a backslash. Write `{{` and `}}`
+    /// for literal braces.
+    #[arg(long, global = true)]
+    template: Option<String>,
+
+    /// Discord webhook URL the discord format posts releases to.
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "CROSRELEASENOTIFIER_DISCORD_WEBHOOK",
+        hide_env_values = true,
+        global = true
+    )]
+    discord_webhook: Option<String>,
+
+    /// File to read the --discord-webhook URL from, which unlike the flag doesn't show up in
+    /// shell history or process listings.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "discord_webhook",
+        global = true
+    )]
+    discord_webhook_file: Option<PathBuf>,
+
+    /// What to turn underlined text into, as the decorators have no underline.
+    #[arg(long, value_enum, default_value_t = UnderlineAs::Strong, global = true)]
+    underline_as: UnderlineAs,
+
+    /// Keep HTML entities such as `&amp;` that are left in the text after converting the HTML,
+    /// instead of decoding them.
+    #[arg(long, global = true)]
+    no_entity_decode: bool,
 
     /// Disable filtering the release HTML to remove boilerplate.
-    #[arg(short = 'F', long = "no-filter")]
+    #[arg(short = 'F', long = "no-filter", global = true)]
     unfiltered: bool,
 
+    /// Keep the lines the boilerplate filter removes, prefixed with `[filtered] `, to see what it
+    /// cuts. Repeated lines are still collapsed.
+    #[arg(long, global = true)]
+    show_filtered: bool,
+
     /// Number of releases to fetch from the feed. This does NOT correspond to the number of
     /// releases returned.
     ///
-    /// This may not work as you expect. The feed caps the number at some unknown max value.
+    /// This may not work as you expect. The feed caps the number at some unknown max value. Use
+    /// --paginate to fetch at least this many ChromeOS releases regardless.
     #[arg(short, long, default_value_t = 25)]
     releases: u32,
 
+    /// Fetch further pages of the feed until it has --releases ChromeOS releases, instead of only
+    /// the first page, which the feed caps.
+    #[arg(long)]
+    paginate: bool,
+
     /// Start index of releases to fetch from the feed. This does NOT correspond to the number of
     /// releases returned.
     #[arg(short, long, default_value_t = 1)]
@@ -53,172 +326,3503 @@ struct Cli {
 
     /// Store and use a timestamp to only track new releases.
     ///
-    /// The timestamp is stored in the XDG Cache Directory in the folder crosreleasenotifier.
+    /// The timestamp is stored in the XDG Cache Directory in the folder crosreleasenotifier, or in
+    /// --cache-dir if given. The versions seen so far are stored next to it, so posts announcing
+    /// a version another post already announced are flagged as re-releases.
     #[arg(short, long)]
     diff: bool,
+
+    /// Store the releases fetched in the cache directory, for --from-cache.
+    #[arg(long)]
+    cache_releases: bool,
+
+    /// Output the releases stored by the last run with --cache-releases instead of fetching the
+    /// feed. Fails if they were fetched with different feed or filter options.
+    #[arg(long, alias = "offline", conflicts_with = "cache_releases")]
+    from_cache: bool,
+
+    /// What --diff remembers to tell new releases apart. Id keeps the ids of the last 500
+    /// releases shown, which stay the same when a post is edited, so releases published close
+    /// together or back-dated aren't missed. Timestamp keeps the newest release's timestamp, so
+    /// an edited post shows up again. Content-hash keeps hashes of the content of the last 500
+    /// releases shown, so a post republished with the same content isn't shown again even with a
+    /// new id. Switching to id or content-hash carries over the releases seen before.
+    #[arg(long, value_enum, default_value_t = DiffBy::Id, requires = "diff")]
+    diff_by: DiffBy,
+
+    /// Forget the releases seen by previous --diff runs, so this run is a first run again.
+    #[arg(long, requires = "diff")]
+    diff_reset: bool,
+
+    /// What the first --diff run does, when nothing has been remembered yet. Mark-only avoids
+    /// a flood of notifications for every release in the feed.
+    #[arg(long, value_enum, default_value_t = FirstRun::MarkOnly, requires = "diff")]
+    first_run: FirstRun,
+
+    /// Append the releases shown to this file, one JSON object per line, oldest first.
+    #[arg(long, global = true)]
+    history: Option<PathBuf>,
+
+    /// Keep only the newest N releases in the --history file. Unbounded by default.
+    #[arg(long, requires = "history", global = true)]
+    history_max: Option<usize>,
+
+    /// Directory to store cached data such as the --diff timestamp in, instead of the XDG Cache
+    /// Directory. Created if it doesn't exist.
+    #[arg(long, env = "CROSRELEASENOTIFIER_CACHE_DIR", global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Feed category of ChromeOS releases. Can be given multiple times, replacing the default
+    /// categories. Compared ignoring case and whitespace.
+    #[arg(long = "category", value_name = "TERM", default_values_t = CHROMEOS_CATEGORIES.map(String::from), global = true)]
+    categories: Vec<String>,
+
+    /// Feed category of ChromeOS Flex releases, used by --flex-only and --no-flex. Can be given
+    /// multiple times, replacing the default categories.
+    #[arg(long = "flex-category", value_name = "TERM", default_values_t = FLEX_CATEGORIES.map(String::from), global = true)]
+    flex_categories: Vec<String>,
+
+    /// Match entries whose categories contain a --category or --flex-category instead of only
+    /// the ones equal to one.
+    #[arg(long, global = true)]
+    category_contains: bool,
+
+    /// Treat every feed entry as a ChromeOS release, for feeds that don't categorize them.
+    #[arg(long, conflicts_with_all = ["categories", "flex_categories", "category_contains", "flex_only", "no_flex"], global = true)]
+    no_category_filter: bool,
+
+    /// Only show ChromeOS Flex releases.
+    #[arg(long, conflicts_with = "no_flex", global = true)]
+    flex_only: bool,
+
+    /// Don't show ChromeOS Flex releases.
+    #[arg(long, global = true)]
+    no_flex: bool,
+
+    /// Exit with an error if the feed has no ChromeOS releases at all, before --diff or any
+    /// other filtering of releases. This tells a broken category filter apart from there being
+    /// no new releases. With the diff command the NEW feed is checked.
+    #[arg(long, global = true)]
+    fail_on_empty: bool,
+
+    /// Exit with status 10 if --diff found new releases, after updating its cache, and 0 if it
+    /// found none. Errors exit with 1.
+    #[arg(long, requires = "diff")]
+    exit_code: bool,
+
+    /// Phrase marking the start of the boilerplate footer of a release, removed along with
+    /// everything after it unless --no-filter is given. Only lines near the end of the release
+    /// count, so the phrase can also appear in the body. Can be given multiple times, replacing
+    /// the default phrases.
+    #[arg(long = "footer-phrase", value_name = "STR", default_values_t = DEFAULT_FOOTER_PHRASES.map(String::from), global = true)]
+    footer_phrases: Vec<String>,
+
+    /// Only show releases for this channel, taken from the line announcing the update. Releases
+    /// whose channel isn't known are only shown with all.
+    #[arg(short, long, value_enum, default_value_t = ChannelFilter::All, global = true)]
+    channel: ChannelFilter,
+
+    /// Don't show Canary channel releases.
+    #[arg(long, conflicts_with = "canary_summary", global = true)]
+    no_canary: bool,
+
+    /// Collapse the Canary channel releases into a single release listing each of their
+    /// announcements, keeping the newest one's title and date.
+    #[arg(long, global = true)]
+    canary_summary: bool,
+
+    /// Parse tables of boards and the versions they were updated to into board_versions.
+    #[arg(long, global = true)]
+    parse_boards: bool,
+
+    /// Maximum length of the summary taken from the first line of a release when it has no line
+    /// announcing the update.
+    #[arg(long, default_value_t = 200, global = true)]
+    summary_length: usize,
+
+    /// Maximum length of the content of each release, cut with an ellipsis and the release's
+    /// link. Defaults to the limit of the format, 256 for notifications and none for json and
+    /// pretty.
+    #[arg(long, value_name = "N", global = true)]
+    max_content_length: Option<usize>,
+
+    /// Only show releases whose Chrome browser version is at least this one, e.g. `121` or
+    /// `121.0.6167.139`, compared numerically per component. This is the version of Chrome,
+    /// not of the OS image; use --platform-min for that. Releases without a Chrome version are
+    /// kept.
+    #[arg(long, global = true)]
+    version_min: Option<ChromeVersion>,
+
+    /// Only show releases whose platform version, the version of the OS image such as
+    /// `15886.44.0`, is at least this one. Unlike --version-min, which compares the Chrome
+    /// browser version such as `121.0.6167.139`, this never looks at the browser version.
+    /// Releases without a platform version are kept.
+    #[arg(long, value_name = "VERSION", global = true)]
+    platform_min: Option<ChromeVersion>,
+
+    /// Only show the newest N releases of each channel. Releases whose channel isn't known are
+    /// limited together as one more channel.
+    #[arg(long, value_name = "N", global = true)]
+    limit_per_channel: Option<usize>,
+
+    /// Fuzzy-match releases against a query, keeping only releases with a matching line.
+    #[arg(long, global = true)]
+    search: Option<String>,
+
+    /// Order to sort releases in. Relevance only applies when searching.
+    #[arg(long, value_enum, default_value_t = SortOrder::Date, global = true)]
+    sort: SortOrder,
+
+    /// Keep releases in the order of the feed instead of sorting them newest first.
+    #[arg(long, global = true)]
+    no_sort: bool,
+
+    /// Section the pretty and json output, newest milestone first. Releases without a
+    /// milestone are grouped under Unknown at the end. Within a group, releases keep --sort.
+    #[arg(long, value_enum, global = true)]
+    group_by: Option<GroupBy>,
+
+    /// Wrap each release in a collapsed `<details>` block, summarized by its title, version and
+    /// date, for changelogs rendered on GitHub or Discourse. Needs the markdown decorator and the
+    /// pretty format.
+    #[arg(long, global = true)]
+    collapsible: bool,
+
+    /// Give each release a markdown heading with an explicit anchor such as
+    /// `{#m120-stable-2024-01-15}`, for jump links in a changelog of many releases. Needs the
+    /// markdown decorator and the pretty format.
+    #[arg(long, conflicts_with = "collapsible", global = true)]
+    anchors: bool,
+
+    /// List the releases with links to their --anchors at the top.
+    #[arg(long, requires = "anchors", global = true)]
+    toc: bool,
+
+    /// Show a single notification summarizing all releases instead of one per release.
+    #[arg(long, global = true)]
+    notify_batch: bool,
+
+    /// Wait this many milliseconds between notifications, so the notification daemon isn't
+    /// sent more at once than it can show. Ignored with --notify-batch.
+    #[arg(long, value_name = "MS", default_value_t = 0, global = true)]
+    notify_throttle: u64,
+
+    /// Application name to show notifications under. Windows toasts are always shown under
+    /// PowerShell, as another name needs an installed app.
+    #[arg(long, default_value = "crosreleasenotifier", global = true)]
+    notify_appname: String,
+
+    /// Icon for notifications, either an icon theme name or a path to an image. A path that
+    /// doesn't exist is skipped with a warning. Windows toasts only show paths, and clicking them
+    /// doesn't open the release.
+    #[arg(long, default_value = "chromium", global = true)]
+    notify_icon: String,
+
+    /// Language of the notification titles. Release summaries come from the feed and batch
+    /// summaries are always in English.
+    #[arg(long, value_enum, default_value_t = Lang::En, global = true)]
+    lang: Lang,
+
+    /// Only print how many releases would be output, as `{"count": N}` with the json format.
+    ///
+    /// The --diff timestamp isn't updated, so the releases are still new on the next run.
+    #[arg(long, global = true)]
+    count_only: bool,
+
+    /// Print what side-effecting formats such as notification would send instead of sending it.
+    ///
+    /// The --diff timestamp isn't updated either, so the releases are still new on the next run.
+    /// Formats that only print, like json and pretty, ignore this.
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Prefix release titles in the pretty format with an emoji for their channel.
+    #[arg(long, global = true)]
+    emoji: bool,
+
+    /// Show how long ago releases were released, e.g. `3 days ago`, in the pretty format and
+    /// notifications, followed by the date.
+    #[arg(long, global = true)]
+    relative_time: bool,
+
+    /// Timezone the pretty format and notifications show release times in: utc, local, an IANA
+    /// name such as `Europe/Berlin` or an offset such as `+05:30`. Other formats always use UTC.
+    #[arg(long, default_value = "utc", global = true)]
+    timezone: Timezone,
+
+    /// Show the pretty format in a pager when printing to a terminal.
+    ///
+    /// The pager is taken from the PAGER environment variable, defaulting to `less -R`.
+    #[arg(long, global = true)]
+    pager: bool,
+
+    /// ID of the Blogger blog to fetch releases from.
+    #[arg(long, default_value = "8982037438137564684", value_parser = parse_blog_id)]
+    blog_id: String,
+
+    /// Feed URL to fetch releases from instead of the Blogger feed of --blog-id, e.g. for testing.
+    /// The paging parameters are still added, and releases are still filtered by category.
+    #[arg(long, value_name = "URL", conflicts_with = "blog_id")]
+    feed_url: Option<String>,
+
+    /// Only fetch posts published at or after this date, e.g. `2024-01-15`. The feed filters these
+    /// itself, so --releases counts only the posts in range.
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg)]
+    published_min: Option<DateTime<Utc>>,
+
+    /// Only fetch posts published before this date, e.g. `2024-02-01`.
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg)]
+    published_max: Option<DateTime<Utc>>,
+
+    /// Only show releases updated at or after this date, e.g. `2024-01-15`. Unlike
+    /// --published-min this filters the releases fetched, by the same timestamp --diff uses.
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg, global = true)]
+    since: Option<DateTime<Utc>>,
+
+    /// Only show releases updated before this date (exclusive), e.g. `2024-02-01`. It has to
+    /// be after --since.
+    #[arg(long, value_name = "DATE", value_parser = parse_date_arg, global = true)]
+    until: Option<DateTime<Utc>>,
+
+    /// Feed URL to fall back to if the feed can't be fetched or parsed. Can be given multiple
+    /// times, mirrors are tried in order.
+    #[arg(long = "feed-mirror", value_name = "URL")]
+    feed_mirrors: Vec<String>,
+
+    /// Bearer token to send to the --feed-mirror URLs. Never sent to the Blogger feed.
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "CROSRELEASENOTIFIER_AUTH_BEARER",
+        hide_env_values = true,
+        conflicts_with_all = ["auth_basic", "auth_basic_file"]
+    )]
+    auth_bearer: Option<String>,
+
+    /// File to read the --auth-bearer token from, which unlike the flag doesn't show up in shell
+    /// history or process listings.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["auth_bearer", "auth_basic", "auth_basic_file"]
+    )]
+    auth_bearer_file: Option<PathBuf>,
+
+    /// Username and password to send to the --feed-mirror URLs with basic authentication.
+    /// Never sent to the Blogger feed.
+    #[arg(
+        long,
+        value_name = "USER:PASS",
+        value_parser = parse_basic_auth,
+        env = "CROSRELEASENOTIFIER_AUTH_BASIC",
+        hide_env_values = true
+    )]
+    auth_basic: Option<(String, String)>,
+
+    /// File to read the --auth-basic USER:PASS from.
+    #[arg(long, value_name = "PATH", conflicts_with = "auth_basic")]
+    auth_basic_file: Option<PathBuf>,
+
+    /// Header to send with the feed requests, to the Blogger feed and the --feed-mirror URLs
+    /// alike. Can be given multiple times.
+    #[arg(long = "header", value_name = "KEY:VALUE", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// How many times to retry fetching the feed from a URL after a network error or a server
+    /// error status. Other errors, such as a client error status or a feed that can't be parsed,
+    /// aren't retried.
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+
+    /// Milliseconds to wait before the first retry, doubling for each one after it.
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    retry_delay_ms: u64,
+
+    /// Maximum number of redirects to follow when fetching the feed. Redirects to another host
+    /// are logged as warnings.
+    #[arg(long, default_value_t = 5)]
+    max_redirects: usize,
+
+    /// Don't follow redirects when fetching the feed.
+    #[arg(long, conflicts_with = "max_redirects")]
+    no_redirects: bool,
+
+    /// If the feed is malformed, parse its entries one by one and output the ones that parse,
+    /// instead of failing. How many entries were skipped is logged as a warning.
+    #[arg(long, global = true)]
+    lenient: bool,
+
+    /// Format of the logs written to stderr. Json logs include the events for each run, such as
+    /// how long fetching took and how many new releases were found, without needing -v.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
+    /// Print more information. Shows search scores in the pretty format, and logs progress to
+    /// stderr. Repeat for more detailed logs.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only print errors. Formats that just print, like json and pretty, are skipped, so only
+    /// side effects such as notifications and the exit status remain.
+    #[arg(short, long, conflicts_with_all = ["verbose", "count_only"], global = true)]
+    quiet: bool,
+}
+
+impl Cli {
+    /// The decorator given, or the default one of the first format.
+    fn decorator(&self) -> Decorator {
+        self.decorator
+            .unwrap_or_else(|| self.format[0].default_decorator())
+    }
+}
+
+/// Rewrites definition lists into a bold paragraph per term followed by its indented
+/// definitions. html2text renders terms as emphasis and indents definitions without a blank
+/// line, which markdown renderers merge into the term's paragraph, so each definition is put in
+/// a `<dl>` of its own after the term's paragraph instead.
+fn flatten_definition_lists(html: String) -> String {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| Regex::new(r"(?i)<(/?)(dl|dt|dd)\b[^>]*>").unwrap());
+    tag.replace_all(&html, |x: &regex::Captures| {
+        let close = !x[1].is_empty();
+        match (x[2].to_ascii_lowercase().as_str(), close) {
+            ("dl", false) => "<div>",
+            ("dl", true) => "</div>",
+            ("dt", false) => "<p><strong>",
+            ("dt", true) => "</strong></p>",
+            ("dd", false) => "<dl><dd>",
+            _ => "</dd></dl>",
+        }
+    })
+    .into_owned()
 }
 
-fn html2md(html: String, decorator: Decorator) -> String {
+fn html2md(html: String, decorator: Decorator, gfm: bool, width: usize) -> String {
+    let html = flatten_definition_lists(html);
     match decorator {
+        Decorator::Markdown if gfm => {
+            let decorator = MdDecorator::gfm();
+            finish_gfm(&render_markdown(html.as_bytes(), width, decorator))
+        }
         Decorator::Markdown => {
             let decorator = MdDecorator::new();
-            html2text::from_read_with_decorator(html.as_bytes(), usize::MAX, decorator)
+            render_markdown(html.as_bytes(), width, decorator)
         }
         Decorator::Plain => {
             let decorator = PlainDecorator::new();
-            html2text::from_read_with_decorator(html.as_bytes(), usize::MAX, decorator)
+            html2text::from_read_with_decorator(html.as_bytes(), width, decorator)
+        }
+        #[cfg(feature = "decorator-bbcode")]
+        Decorator::BBCode => {
+            let decorator = BBCodeDecorator::new();
+            render_bbcode(html.as_bytes(), width, decorator)
+        }
+        #[cfg(feature = "decorator-rst")]
+        Decorator::Rst => {
+            let decorator = RstDecorator::new();
+            render_rst(html.as_bytes(), width, decorator)
+        }
+        // Finished by render_content once entities left in the text are decoded, so they're
+        // only escaped once.
+        #[cfg(feature = "decorator-html")]
+        Decorator::Html => {
+            let decorator = HtmlDecorator::new();
+            render_html(html.as_bytes(), width, decorator)
+        }
+    }
+}
+
+/// Converts the HTML of a release with the --decorator and decodes the entities left in it.
+fn render_content(body: Option<String>, opts: &Cli) -> String {
+    // The filter matches phrases within a line, so paragraphs are left unwrapped. The formats
+    // for reading wrap the content when they show it instead.
+    let (underline_open, underline_close) = match (opts.underline_as, opts.decorator()) {
+        // Written with the marks of the decorator so finish_html keeps them as tags.
+        #[cfg(feature = "decorator-html")]
+        (UnderlineAs::Html, Decorator::Html) => (html_tag("<u>"), html_tag("</u>")),
+        (underline_as, _) => {
+            let (open, close) = underline_as.tags();
+            (open.to_string(), close.to_string())
         }
+    };
+    let parsed = body
+        .map(|x| {
+            html2md(
+                x.replace("<u>", &underline_open)
+                    .replace("</u>", &underline_close),
+                opts.decorator(),
+                opts.gfm,
+                usize::MAX,
+            )
+        })
+        .unwrap_or_else(|| "No content.".to_string());
+    let parsed = if opts.no_entity_decode {
+        parsed
+    } else {
+        decode_entities(&parsed).into_owned()
+    };
+    match opts.decorator() {
+        #[cfg(feature = "decorator-html")]
+        Decorator::Html => finish_html(&parsed),
+        _ => parsed,
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Release {
+    /// The feed entry's id, which stays the same when the post is edited.
+    id: String,
+    /// The category terms of the feed entry, e.g. `ChromeOS` or `ChromeOS Flex`.
+    categories: Vec<String>,
+    /// URL of the release's blog post.
+    link: Option<String>,
+    /// Name of the post's first author, usually the release manager.
+    author: Option<String>,
     title: String,
     summary: String,
     content: String,
+    /// The boilerplate footer the filter removed from the content, from the first
+    /// --footer-phrase on. Only set when filtering.
+    footer: Option<String>,
     timestamp: DateTime<Utc>,
+    bug_ids: Vec<String>,
+    /// The first Chrome browser version in the content, e.g. `126.0.6478.132`.
+    chrome_version: Option<String>,
+    /// The first platform version in the content, e.g. `15886.44.0`.
+    platform_version: Option<String>,
+    /// Every Chrome version in the content in order of first appearance, for posts announcing
+    /// updates to several channels. The first one is `chrome_version`.
+    chrome_versions: Vec<String>,
+    /// Every platform version in the content in order of first appearance. The first one is
+    /// `platform_version`.
+    platform_versions: Vec<String>,
+    /// Version of the next older release on the same channel, if it was fetched too.
+    previous_version: Option<String>,
+    channel: Option<Channel>,
+    /// Chrome milestone, e.g. 120 for M120 or LTS-120.
+    milestone: Option<u32>,
+    /// Whether this is a long-term support release, including the LTC candidates for it.
+    is_lts: bool,
+    /// Whether the release notes list security fixes.
+    security_fixes: bool,
+    /// Boards and the versions they were updated to, from a table in the release notes. Only
+    /// parsed with --parse-boards.
+    board_versions: Vec<(String, String)>,
+    /// Whether another post announced this version before. Only tracked with --diff.
+    rereleased: bool,
+    /// Whether the release is newer than the ones seen by the last --diff run. Without --diff
+    /// releases aren't filtered by this, and all of them are new if --diff was never used.
+    is_new: bool,
+    #[serde(skip)]
+    score: Option<i64>,
+    /// Hash of the entry's HTML, which unlike the content doesn't depend on the decorator or
+    /// width used.
+    #[serde(skip)]
+    source_hash: String,
 }
 
-async fn get_releases(opts: &Cli) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
-    let body = reqwest::get(format!(
-        "https://www.blogger.com/feeds/8982037438137564684/posts/default?start-index={}&max-results={}",
-        opts.start,
-        opts.releases
-    ))
-    .await?
-    .bytes()
-    .await?
-    .reader();
-    let feed = feed_rs::parser::parse(body)?;
-    Ok(feed
-        .entries
-        .into_iter()
-        .filter(|x| {
-            x.categories.iter().map(|x| &x.term).any(|x| {
-                x == "ChromeOS" || x == "Chrome OS" || x == "ChromeOS Flex" || x == "Chrome OS Flex"
-            })
-        })
-        .filter_map(|x| Some((x.title?, x.content?, x.updated?)))
-        .map(|(title, content, updated)| {
-            let parsed = content
-                .body
-                .map(|x| {
-                    html2md(
-                        x.replace("<u>", "<strong>").replace("</u>", "</strong>"),
-                        opts.decorator,
-                    )
-                })
-                .unwrap_or_else(|| "No content.".to_string());
+impl Release {
+    /// The version identifying this release, preferring the platform version as that's the one
+    /// the OS image is built from.
+    fn version(&self) -> Option<&str> {
+        self.platform_version
+            .as_deref()
+            .or(self.chrome_version.as_deref())
+    }
 
-            let mut should_filter = !opts.unfiltered;
+    /// Describes the Chrome and platform versions, e.g. `Chrome 126.0.6478.132 / Platform
+    /// 15886.44.0`, leaving out the ones that weren't found.
+    fn version_line(&self) -> Option<String> {
+        let chrome = self
+            .chrome_version
+            .as_ref()
+            .map(|x| format!("Chrome {}", x));
+        let platform = self
+            .platform_version
+            .as_ref()
+            .map(|x| format!("Platform {}", x));
+        match (chrome, platform) {
+            (Some(chrome), Some(platform)) => Some(format!("{} / {}", chrome, platform)),
+            (chrome, platform) => chrome.or(platform),
+        }
+    }
+}
 
-            let mut lines: Vec<&str> = parsed.split('\n').collect();
-            if should_filter {
-                lines.dedup();
-                lines.truncate(lines.len().saturating_sub(4));
+/// Drops the releases below --version-min, which compares the Chrome version, and
+/// --platform-min, which compares the platform version. Releases without the version compared
+/// are kept.
+fn filter_versions(releases: &mut Vec<Release>, opts: &Cli) {
+    let at_least = |version: Option<&str>, min: &Option<ChromeVersion>| match (
+        version.and_then(ChromeVersion::parse),
+        min,
+    ) {
+        (Some(version), Some(min)) => version >= *min,
+        _ => true,
+    };
+    releases.retain(|x| {
+        at_least(x.chrome_version.as_deref(), &opts.version_min)
+            && at_least(x.platform_version.as_deref(), &opts.platform_min)
+    });
+}
+
+/// Collects bug references such as `issue 123456`, `crbug/123456` and `b/123456` from the
+/// content, in order of first appearance. Chromium issues are normalized to `crbug/<id>`.
+fn parse_bug_ids(content: &str) -> Vec<String> {
+    static BUG: OnceLock<Regex> = OnceLock::new();
+    let bug = BUG.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b(?:b/(?P<buganizer>\d{5,})|(?:issues?\s+|crbug(?:\.com)?/|issues\.chromium\.org/issues/)(?P<crbug>\d{5,}))",
+        )
+        .unwrap()
+    });
+    let mut ids: Vec<String> = Vec::new();
+    for captures in bug.captures_iter(content) {
+        let id = match (captures.name("buganizer"), captures.name("crbug")) {
+            (Some(id), _) => format!("b/{}", id.as_str()),
+            (_, Some(id)) => format!("crbug/{}", id.as_str()),
+            _ => continue,
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// Finds the Chrome browser versions (e.g. `126.0.6478.132`) and platform versions (e.g.
+/// `15886.44.0`) mentioned in the content, each in order of first appearance.
+fn parse_versions(content: &str) -> (Vec<String>, Vec<String>) {
+    static CHROME: OnceLock<Regex> = OnceLock::new();
+    static PLATFORM: OnceLock<Regex> = OnceLock::new();
+    let chrome = CHROME.get_or_init(|| Regex::new(r"\b\d+\.\d+\.\d+\.\d+\b").unwrap());
+    let platform = PLATFORM
+        .get_or_init(|| Regex::new(r"(?i)platform\s+version:?\s*(\d+\.\d+\.\d+)\b").unwrap());
+    let mut chrome_versions: Vec<String> = Vec::new();
+    for x in chrome.find_iter(content) {
+        if !chrome_versions.iter().any(|y| y == x.as_str()) {
+            chrome_versions.push(x.as_str().into());
+        }
+    }
+    let mut platform_versions: Vec<String> = Vec::new();
+    for x in platform.captures_iter(content) {
+        if !platform_versions.contains(&x[1].to_string()) {
+            platform_versions.push(x[1].into());
+        }
+    }
+    (chrome_versions, platform_versions)
+}
+
+const DEFAULT_FOOTER_PHRASES: [&str; 3] = [
+    "If you find new issues",
+    "Interested in switching",
+    "Google Chrome Team",
+];
+
+/// Lines without a footer phrase the footer can have between and after its phrases, such as the
+/// list of ways to report issues and the signature.
+const FOOTER_MAX_GAP: usize = 6;
+
+/// Finds where the boilerplate footer starts, at the first line of the trailing block with a
+/// --footer-phrase. The block is searched from the end and ends at more than
+/// `FOOTER_MAX_GAP` lines without a phrase, so phrases in the body don't cut it off.
+fn footer_start(lines: &[&str], phrases: &[String]) -> Option<usize> {
+    let mut start = None;
+    let mut gap = 0;
+    for (i, line) in lines.iter().enumerate().rev() {
+        if phrases.iter().any(|phrase| line.contains(phrase.as_str())) {
+            start = Some(i);
+            gap = 0;
+        } else if !line.trim().is_empty() {
+            gap += 1;
+            if gap > FOOTER_MAX_GAP {
+                break;
             }
+        }
+    }
+    start
+}
 
-            let mut filtered: Vec<&str> = Vec::new();
-            let mut summary = String::new();
+/// Collects `(board, version)` pairs from the rows of the tables in the HTML, taking the first
+/// cell as the board and the first later cell with a version in it as its version. Rows without
+/// a version, such as headers, are skipped.
+fn parse_board_versions(html: &str) -> Vec<(String, String)> {
+    static ROW: OnceLock<Regex> = OnceLock::new();
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let row = ROW.get_or_init(|| Regex::new(r"(?is)<tr\b.*?</tr>").unwrap());
+    let cell = CELL.get_or_init(|| Regex::new(r"(?is)<t[dh]\b[^>]*>(.*?)</t[dh]>").unwrap());
+    let tag = TAG.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+    let text = |html: &str| {
+        tag.replace_all(html, "")
+            .replace("&nbsp;", " ")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    row.find_iter(html)
+        .filter_map(|x| {
+            let cells: Vec<String> = cell
+                .captures_iter(x.as_str())
+                .map(|x| text(&x[1]))
+                .collect();
+            let (board, rest) = cells.split_first()?;
+            let found = rest.iter().find_map(|x| ChromeVersion::find(x))?;
+            Some((board.clone(), found.to_string()))
+        })
+        .filter(|(board, _)| !board.is_empty())
+        .collect()
+}
 
-            for line in lines.iter() {
-                if should_filter {
-                    if line.contains("is being updated")
-                        || line.contains("has been updated")
-                        || line.contains("is updated in")
-                        || line.contains("was updated in")
-                        || line.contains("has been promoted to")
-                        || line.contains("A new LT")
-                        || line.contains("The new LT")
-                    {
-                        let formatted = line
-                            .split("Want to know")
-                            .next()
-                            .map(|x| x.trim())
-                            .unwrap_or(line);
-                        summary = formatted.into();
-                        filtered.push(formatted);
-                    } else if line.contains("See the latest release")
-                        || line.contains("Release notes for")
-                    {
-                        filtered.push(line);
-                    } else if line.contains("This update contains selective Security fixes")
-                        || line.contains("This update contains selected Security fixes")
-                        || line.contains("This update contains multiple Security fixes")
-                        || line.contains("ChromeOS Vulnerability Bug Fixes")
-                        || line.contains("Security Fixes And Rewards")
-                    {
-                        filtered.push("");
-                        filtered.push(line);
-                        should_filter = false;
-                    }
-                } else {
-                    filtered.push(line);
-                }
+/// Decodes HTML entities left in text, such as `&amp;` or `&#x27;` in doubly escaped feed
+/// content. Unknown entities are kept as they are.
+fn decode_entities(text: &str) -> Cow<'_, str> {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let entity = ENTITY.get_or_init(|| {
+        Regex::new(r"&(?:#([0-9]{1,7})|#[xX]([0-9a-fA-F]{1,6})|([a-zA-Z]+));").unwrap()
+    });
+    entity.replace_all(text, |captures: &regex::Captures| {
+        let decoded = if let Some(decimal) = captures.get(1) {
+            decimal.as_str().parse().ok().and_then(char::from_u32)
+        } else if let Some(hex) = captures.get(2) {
+            u32::from_str_radix(hex.as_str(), 16)
+                .ok()
+                .and_then(char::from_u32)
+        } else {
+            match &captures[3] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "lsquo" => Some('‘'),
+                "rsquo" => Some('’'),
+                "ldquo" => Some('“'),
+                "rdquo" => Some('”'),
+                "hellip" => Some('…'),
+                "copy" => Some('©'),
+                "reg" => Some('®'),
+                "trade" => Some('™'),
+                _ => None,
             }
-            Release {
-                title: title.content,
-                summary,
-                content: filtered.join("\n"),
-                timestamp: updated,
+        };
+        match decoded {
+            Some(x) => x.to_string(),
+            None => captures[0].to_string(),
+        }
+    })
+}
+
+/// Shortens a line to at most `length` characters, ending it with an ellipsis if cut.
+fn truncate_summary(line: &str, length: usize) -> String {
+    if line.chars().count() <= length {
+        return line.into();
+    }
+    let cut: String = line.chars().take(length.saturating_sub(1)).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Shortens content to at most `length` characters, ending it with an ellipsis and the link to
+/// the full release if cut. It's cut between characters, so multibyte ones are never split.
+fn truncate_content(content: &str, length: usize, link: Option<&str>) -> String {
+    if content.chars().count() <= length {
+        return content.into();
+    }
+    let suffix = match link {
+        Some(link) if link.chars().count() + 2 < length => format!("…\n{}", link),
+        _ => "…".into(),
+    };
+    let cut: String = content
+        .chars()
+        .take(length.saturating_sub(suffix.chars().count()))
+        .collect();
+    format!("{}{}", cut.trim_end(), suffix)
+}
+
+/// Whether a line is the one announcing which channel was updated to which version.
+fn is_announcement(line: &str) -> bool {
+    line.contains("is being updated")
+        || line.contains("has been updated")
+        || line.contains("is updated in")
+        || line.contains("was updated in")
+        || line.contains("has been promoted to")
+        || line.contains("A new LT")
+        || line.contains("The new LT")
+}
+
+/// Whether a line starts the list of security fixes in a release.
+fn is_security_heading(line: &str) -> bool {
+    line.contains("This update contains selective Security fixes")
+        || line.contains("This update contains selected Security fixes")
+        || line.contains("This update contains multiple Security fixes")
+        || line.contains("ChromeOS Vulnerability Bug Fixes")
+        || line.contains("Security Fixes And Rewards")
+}
+
+/// Finds whether an announcement is for the LTS channel and the milestone it names, which LTS
+/// announcements give as e.g. `LTS-120` or `LTC-126` rather than only in the version:
+///
+/// - "LTS-120 is being updated in the LTS channel to 120.0.6099.288 (Platform Version:
+///   15662.115.0) for most ChromeOS devices."
+/// - "A new LTC version 126.0.6478.222 (Platform Version: 15886.76.0) is rolling out for most
+///   ChromeOS devices."
+fn parse_lts(announcement: &str) -> (bool, Option<u32>) {
+    static LTS: OnceLock<Regex> = OnceLock::new();
+    let lts = LTS.get_or_init(|| Regex::new(r"\bLT[SC](?:-(\d+))?\b").unwrap());
+    match lts.captures(announcement) {
+        Some(captures) => (true, captures.get(1).and_then(|x| x.as_str().parse().ok())),
+        None => (false, None),
+    }
+}
+
+/// Finds the channel an announcement is for, e.g. "The Beta channel has been updated to ...".
+/// A channel the announcement says another was promoted or updated to is the one released to,
+/// as in "The Beta channel has been promoted to Stable".
+fn parse_channel(announcement: &str, is_lts: bool) -> Option<Channel> {
+    static CHANNEL: OnceLock<Regex> = OnceLock::new();
+    static TARGET: OnceLock<Regex> = OnceLock::new();
+    if is_lts {
+        return Some(Channel::Lts);
+    }
+    let channel = CHANNEL.get_or_init(|| Regex::new(r"(?i)\b(stable|beta|dev|canary)\b").unwrap());
+    let target = TARGET.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:promoted|updated) to (?:the )?(stable|beta|dev|canary)\b").unwrap()
+    });
+    let found = target
+        .captures(announcement)
+        .and_then(|x| x.get(1))
+        .or_else(|| channel.find(announcement))?;
+    match found.as_str().to_lowercase().as_str() {
+        "stable" => Some(Channel::Stable),
+        "beta" => Some(Channel::Beta),
+        "dev" => Some(Channel::Dev),
+        "canary" => Some(Channel::Canary),
+        _ => None,
+    }
+}
+
+/// Stands in for the spaces in markdown links while wrapping, so a link is never split.
+const LINK_SPACE: char = '\u{E020}';
+
+/// Wraps a line of decorated text, keeping continuation lines aligned with the text after any
+/// indentation, list marker or quote prefix. Lines are only broken at spaces outside of links,
+/// so links and URLs stay whole even if they're longer than the width.
+fn wrap_line(line: &str, width: usize) -> String {
+    static PREFIX: OnceLock<Regex> = OnceLock::new();
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let prefix = PREFIX.get_or_init(|| {
+        Regex::new(r"^\s*(?:(?:[*-]|#\.|\d+\.|\[\*\])\s+|\[\*\]|(?:>\s?)+)?").unwrap()
+    });
+    let link = LINK.get_or_init(|| Regex::new(r"\[[^\]\n]*\]\([^)\s]*\)").unwrap());
+    let prefix = prefix.find(line).map_or("", |x| x.as_str());
+    let continuation = if prefix.trim_start().starts_with('>') {
+        prefix.to_string()
+    } else {
+        " ".repeat(prefix.chars().count())
+    };
+    let options = textwrap::Options::new(width)
+        .initial_indent(prefix)
+        .subsequent_indent(&continuation)
+        .word_separator(textwrap::WordSeparator::AsciiSpace)
+        .word_splitter(textwrap::WordSplitter::NoHyphenation)
+        .break_words(false);
+    let text = link.replace_all(&line[prefix.len()..], |x: &regex::Captures| {
+        x[0].replace(' ', &LINK_SPACE.to_string())
+    });
+    textwrap::fill(&text, options).replace(LINK_SPACE, " ")
+}
+
+/// Wraps each line of decorated text with `wrap_line`, except for the lines of code fences
+/// which are kept as they are. Text isn't changed at all without a width to wrap at.
+fn wrap_text(text: &str, width: usize) -> String {
+    if width == usize::MAX {
+        return text.into();
+    }
+    let mut fenced = false;
+    text.split('\n')
+        .map(|x| {
+            if x.trim_start_matches(['>', ' ']).starts_with("```") {
+                fenced = !fenced;
+                x.to_string()
+            } else if fenced {
+                x.to_string()
+            } else {
+                wrap_line(x, width)
             }
         })
-        .collect())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let opts = Cli::parse();
-    let mut releases = get_releases(&opts).await?;
-    releases.sort_by(|x, y| y.timestamp.cmp(&x.timestamp));
-    let xdg = xdg::BaseDirectories::with_prefix("crosreleasenotifier")?;
-    let diff_file = xdg.place_cache_file("last_release")?;
-    if opts.diff
-        && let Ok(diff_date) = std::fs::read(diff_file.clone()).and_then(|x| {
-            serde_json::from_slice::<DateTime<Utc>>(&x).map_err(std::io::Error::other)
-        })
-    {
-        releases.retain(|x| x.timestamp > diff_date)
+/// Reads a secret from a file given in place of its flag. A trailing newline, as most editors
+/// add, isn't part of the secret.
+fn read_secret(path: &PathBuf) -> Result<String, Box<dyn std::error::Error>> {
+    let secret = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Fills in the secrets given through their `-file` flags, so the rest only has to look at the
+/// flag itself. Environment variables are already handled by clap.
+fn resolve_secrets(opts: &mut Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = &opts.auth_bearer_file {
+        opts.auth_bearer = Some(read_secret(path)?);
     }
-    match opts.format {
-        OutputFormat::Json => {
-            serde_json::to_writer(std::io::stdout(), &releases)?;
+    if let Some(path) = &opts.auth_basic_file {
+        opts.auth_basic = Some(parse_basic_auth(&read_secret(path)?)?);
+    }
+    if let Some(path) = &opts.discord_webhook_file {
+        opts.discord_webhook = Some(read_secret(path)?);
+    }
+    Ok(())
+}
+
+fn parse_basic_auth(auth: &str) -> Result<(String, String), String> {
+    match auth.split_once(':') {
+        Some((user, password)) => Ok((user.into(), password.into())),
+        None => Err("expected USER:PASS".into()),
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let Some((name, value)) = header.split_once(':') else {
+        return Err("expected KEY:VALUE".into());
+    };
+    let name = name.trim();
+    let value = value.trim();
+    reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("{:?} isn't a valid header name", name))?;
+    reqwest::header::HeaderValue::from_str(value)
+        .map_err(|_| format!("{:?} isn't a valid header value", value))?;
+    Ok((name.into(), value.into()))
+}
+
+/// Parses an RFC 3339 timestamp, or a date taken as midnight UTC.
+fn parse_date_arg(date: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    match chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_time(chrono::NaiveTime::MIN).and_utc()),
+        Err(_) => Err("expected a date such as 2024-01-15 or an RFC 3339 timestamp".into()),
+    }
+}
+
+fn parse_blog_id(id: &str) -> Result<String, String> {
+    if !id.is_empty() && id.chars().all(|x| x.is_ascii_digit()) {
+        Ok(id.into())
+    } else {
+        Err("blog IDs are numeric".into())
+    }
+}
+
+/// Adds the paging and --published-min/--published-max query parameters to a feed URL.
+fn paged_feed_url(url: &str, opts: &Cli, start: u32) -> String {
+    let mut url = format!(
+        "{}{}start-index={}&max-results={}",
+        url,
+        if url.contains('?') { '&' } else { '?' },
+        start,
+        opts.releases
+    );
+    for (name, date) in [
+        ("published-min", opts.published_min),
+        ("published-max", opts.published_max),
+    ] {
+        if let Some(date) = date {
+            url += &format!("&{}={}", name, date.format("%Y-%m-%dT%H:%M:%SZ"));
         }
-        OutputFormat::Pretty => {
-            if !releases.is_empty() {
-                println!(
-                    "{}",
-                    releases
-                        .iter()
-                        .map(|x| format!(
-                            "============\n{}\nReleased at {}\n============\n{}",
-                            x.title,
-                            x.timestamp.format("%d/%m/%Y %H:%M"),
-                            x.content
-                        ))
-                        .collect::<Vec<_>>()
-                        .join("\n")
+    }
+    url
+}
+
+/// Fails on WASI, which has no sockets to make requests with. reqwest's wasm32 client calls the
+/// JS `fetch`, which would panic there.
+fn check_http() -> Result<(), Box<dyn std::error::Error>> {
+    if cfg!(target_os = "wasi") {
+        return Err(
+            "WASI can't make HTTP requests, read saved feeds with the diff command or cached \
+             releases with --from-cache instead"
+                .into(),
+        );
+    }
+    Ok(())
+}
+
+/// Builds the client every request is made with, following at most --max-redirects redirects.
+/// It's built once and shared so connections to the same host are reused. Browsers handle
+/// redirects themselves on wasm32, so the limit doesn't apply there.
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn http_client(opts: &Cli) -> Result<reqwest::Client, Box<dyn std::error::Error>> {
+    let builder = reqwest::Client::builder();
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = {
+        let max = if opts.no_redirects {
+            0
+        } else {
+            opts.max_redirects
+        };
+        builder.redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            let from = attempt.previous().last().and_then(|x| x.host_str());
+            if from != attempt.url().host_str() {
+                tracing::warn!(
+                    from = from.unwrap_or(""),
+                    to = attempt.url().as_str(),
+                    "redirected to another host"
                 );
             }
-        }
-        OutputFormat::Notification => {
-            for release in releases.iter() {
-                let summary = format!(
-                    "ChromeOS Release on {}",
-                    release.timestamp.format("%Y/%m/%d")
+            if attempt.previous().len() > max {
+                let error = format!(
+                    "redirected to {}, more than {} redirects",
+                    attempt.url(),
+                    max
                 );
-                notify_rust::Notification::new()
-                    .summary(&summary)
-                    .body(&release.summary)
-                    .timeout(notify_rust::Timeout::Never)
-                    .show()?;
+                attempt.error(error)
+            } else {
+                attempt.follow()
             }
+        }))
+    };
+    Ok(builder.build()?)
+}
+
+/// Parses a response while it downloads. The parser is synchronous, so it runs on a blocking
+/// thread reading from the body stream. With --lenient the body is downloaded first instead, so
+/// it can be parsed again entry by entry.
+#[cfg(not(target_arch = "wasm32"))]
+async fn parse_response(
+    response: reqwest::Response,
+    lenient: bool,
+) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    if lenient {
+        return parse_feed(&response.bytes().await?, true);
+    }
+    use futures_util::TryStreamExt;
+    let stream = response.bytes_stream().map_err(std::io::Error::other);
+    let body = tokio_util::io::SyncIoBridge::new(tokio_util::io::StreamReader::new(stream));
+    let feed =
+        tokio::task::spawn_blocking(move || feed_rs::parser::parse(std::io::BufReader::new(body)))
+            .await??;
+    Ok(feed)
+}
+
+/// There are no blocking threads on wasm32, so the body is downloaded before parsing.
+#[cfg(target_arch = "wasm32")]
+async fn parse_response(
+    response: reqwest::Response,
+    lenient: bool,
+) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    parse_feed(&response.bytes().await?, lenient)
+}
+
+/// Parses a feed, falling back to [`recover_feed`] if it's malformed and `lenient` is set.
+fn parse_feed(
+    bytes: &[u8],
+    lenient: bool,
+) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    match feed_rs::parser::parse(bytes) {
+        Ok(feed) => Ok(feed),
+        Err(err) if lenient => {
+            tracing::warn!(error = %err, "failed to parse feed, parsing its entries one by one");
+            recover_feed(&String::from_utf8_lossy(bytes))
         }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Parses each Atom `<entry>` of a malformed feed on its own, skipping the ones that still fail.
+/// Every entry is wrapped in the feed's own root element so the namespaces it declares, like
+/// Blogger's `thr:`, still resolve.
+fn recover_feed(text: &str) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    static ROOT: OnceLock<Regex> = OnceLock::new();
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+    static ENTRY_START: OnceLock<Regex> = OnceLock::new();
+    let root = ROOT
+        .get_or_init(|| Regex::new(r"<feed\b[^>]*>").unwrap())
+        .find(text)
+        .map_or(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#, |x| {
+            x.as_str()
+        });
+    let mut feed = feed_rs::parser::parse(format!("{}</feed>", root).as_bytes())
+        .map_err(|err| format!("failed to recover the feed: {}", err))?;
+    let total = ENTRY_START
+        .get_or_init(|| Regex::new(r"<entry[\s>]").unwrap())
+        .find_iter(text)
+        .count();
+    feed.entries = ENTRY
+        .get_or_init(|| Regex::new(r"(?s)<entry[\s>].*?</entry>").unwrap())
+        .find_iter(text)
+        .filter_map(|x| {
+            let wrapped = format!("{}{}</feed>", root, x.as_str());
+            match feed_rs::parser::parse(wrapped.as_bytes()) {
+                Ok(parsed) => parsed.entries.into_iter().next(),
+                Err(err) => {
+                    tracing::debug!(error = %err, "skipping malformed entry");
+                    None
+                }
+            }
+        })
+        .collect();
+    tracing::warn!(
+        recovered = feed.entries.len(),
+        skipped = total - feed.entries.len(),
+        "recovered entries from malformed feed"
+    );
+    Ok(feed)
+}
+
+/// Adds the --auth-bearer or --auth-basic credentials to a request.
+fn authenticate(request: reqwest::RequestBuilder, opts: &Cli) -> reqwest::RequestBuilder {
+    if let Some(token) = &opts.auth_bearer {
+        request.bearer_auth(token)
+    } else if let Some((user, password)) = &opts.auth_basic {
+        request.basic_auth(user, Some(password))
+    } else {
+        request
     }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(duration: std::time::Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Tokio has no timers on wasm32, and blocking the thread panics in the browser, so this waits
+/// on a JS timer instead.
+#[cfg(target_arch = "wasm32")]
+async fn sleep(duration: std::time::Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Fetches and parses a feed, retrying network errors and server error statuses up to
+/// --retries times with exponential backoff.
+async fn fetch_feed_from(
+    request: reqwest::RequestBuilder,
+    url: &str,
+    opts: &Cli,
+) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    let mut delay = std::time::Duration::from_millis(opts.retry_delay_ms);
+    let mut attempts = 0;
+    let response = loop {
+        attempts += 1;
+        let request = request
+            .try_clone()
+            .ok_or("the feed request can't be retried")?;
+        let error = match request.send().await {
+            Ok(response) if response.status().is_server_error() && attempts <= opts.retries => {
+                response.status().to_string()
+            }
+            Ok(response) => break response,
+            // Connection errors are request errors too. Errors such as too many redirects
+            // would fail the same way again.
+            Err(err) if !err.is_timeout() && !err.is_request() => return Err(err.into()),
+            Err(err) if attempts <= opts.retries => err.to_string(),
+            Err(err) => return Err(format!("{} after {} attempts", err, attempts).into()),
+        };
+        tracing::warn!(url, attempts, error, "failed to fetch feed, retrying");
+        sleep(delay).await;
+        delay *= 2;
+    };
+    tracing::info!(url, status = response.status().as_u16(), "fetched feed");
+    let response = response
+        .error_for_status()
+        .map_err(|err| match err.status() {
+            Some(status) if status.is_server_error() => {
+                format!("{} after {} attempts", err, attempts).into()
+            }
+            _ => Box::<dyn std::error::Error>::from(err),
+        })?;
+    let feed = parse_response(response, opts.lenient).await?;
+    tracing::info!(url, entries = feed.entries.len(), "parsed feed");
+    Ok(feed)
+}
+
+/// The URL of the feed to fetch, before the paging parameters are added.
+fn build_feed_url(opts: &Cli) -> String {
+    match &opts.feed_url {
+        Some(url) => url.clone(),
+        None => format!(
+            "https://www.blogger.com/feeds/{}/posts/default",
+            opts.blog_id
+        ),
+    }
+}
+
+/// Fetches the page of the feed from `start`, falling back to each mirror in order if it can't
+/// be fetched or parsed.
+async fn fetch_feed(
+    client: &reqwest::Client,
+    opts: &Cli,
+    start: u32,
+) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    let mut errors = Vec::new();
+    let primary = build_feed_url(opts);
+    for (i, url) in std::iter::once(&primary)
+        .chain(opts.feed_mirrors.iter())
+        .enumerate()
+    {
+        let url = paged_feed_url(url, opts, start);
+        let mut request = client.get(&url);
+        for (name, value) in opts.headers.iter() {
+            request = request.header(name, value);
+        }
+        // Credentials are for mirrors and --feed-url, and shouldn't be sent to Blogger.
+        if i > 0 || opts.feed_url.is_some() {
+            request = authenticate(request, opts);
+        }
+        match fetch_feed_from(request, &url, opts).await {
+            Ok(feed) => return Ok(feed),
+            Err(err) => {
+                tracing::warn!(url, error = %err, "failed to fetch feed");
+                errors.push(format!("{}: {}", url, err));
+            }
+        }
+    }
+    Err(format!(
+        "failed to fetch the feed from any URL:\n{}",
+        errors.join("\n")
+    )
+    .into())
+}
+
+const CHROMEOS_CATEGORIES: [&str; 2] = ["ChromeOS", "Chrome OS"];
+const FLEX_CATEGORIES: [&str; 2] = ["ChromeOS Flex", "Chrome OS Flex"];
+
+/// Lowercases and strips whitespace so categories compare equal regardless of how a feed
+/// spaces or capitalizes them.
+fn normalize_category(category: &str) -> String {
+    category
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether an entry is a ChromeOS release that passes the Flex filters. Atom categories carry
+/// the name in `term` and sometimes `label`, RSS ones only in the element text which feed_rs
+/// puts in `term`. Entries without any categories are matched on their title, content and
+/// summary mentioning ChromeOS instead.
+fn is_chromeos_entry(entry: &feed_rs::model::Entry, opts: &Cli) -> bool {
+    if opts.no_category_filter {
+        return true;
+    }
+    let exact = !entry.categories.is_empty();
+    let names: Vec<String> = if exact {
+        entry
+            .categories
+            .iter()
+            .flat_map(|x| std::iter::once(&x.term).chain(x.label.as_ref()))
+            .map(|x| normalize_category(x))
+            .collect()
+    } else {
+        entry
+            .title
+            .iter()
+            .map(|x| x.content.as_str())
+            .chain(entry.content.as_ref().and_then(|x| x.body.as_deref()))
+            .chain(entry.summary.as_ref().map(|x| x.content.as_str()))
+            .map(normalize_category)
+            .collect()
+    };
+    let exact = exact && !opts.category_contains;
+    let matches = |categories: &[String]| {
+        categories.iter().map(|x| normalize_category(x)).any(|x| {
+            names
+                .iter()
+                .any(|name| if exact { *name == x } else { name.contains(&x) })
+        })
+    };
+    // Flex releases are usually tagged with both the ChromeOS and ChromeOS Flex categories.
+    let flex = matches(&opts.flex_categories);
+    if opts.flex_only {
+        flex
+    } else if opts.no_flex {
+        !flex && matches(&opts.categories)
+    } else {
+        flex || matches(&opts.categories)
+    }
+}
+
+/// Fetches pages of the feed until they have --releases ChromeOS releases, or a page has fewer
+/// entries than the first one, which is as many as the feed gives per page. Entries already on
+/// an earlier page, as happens when a post is published while paging, are skipped.
+async fn fetch_all_pages(
+    client: &reqwest::Client,
+    opts: &Cli,
+) -> Result<feed_rs::model::Feed, Box<dyn std::error::Error>> {
+    let mut feed = fetch_feed(client, opts, opts.start).await?;
+    let page_size = feed.entries.len();
+    let mut start = opts.start + page_size as u32;
+    let mut matching = feed
+        .entries
+        .iter()
+        .filter(|x| is_chromeos_entry(x, opts))
+        .count();
+    while page_size > 0 && matching < opts.releases as usize {
+        let page = fetch_feed(client, opts, start).await?;
+        let fetched = page.entries.len();
+        let new: Vec<feed_rs::model::Entry> = page
+            .entries
+            .into_iter()
+            .filter(|x| !feed.entries.iter().any(|y| y.id == x.id))
+            .collect();
+        matching += new.iter().filter(|x| is_chromeos_entry(x, opts)).count();
+        tracing::info!(start, entries = fetched, matching, "fetched page");
+        let done = new.is_empty() || fetched < page_size;
+        feed.entries.extend(new);
+        if done {
+            break;
+        }
+        start += fetched as u32;
+    }
+    Ok(feed)
+}
+
+async fn get_releases(
+    client: &reqwest::Client,
+    opts: &Cli,
+) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    check_http()?;
+    let progress = progress_bar(opts, None, "fetching feed");
+    let feed = if opts.paginate {
+        fetch_all_pages(client, opts).await
+    } else {
+        fetch_feed(client, opts, opts.start).await
+    };
+    progress.finish_and_clear();
+    let feed = feed?;
+    tracing::info!(updated = ?feed.updated, "feed last updated");
+    // Without new posts or edits there's nothing new for --diff to show, so the entries don't
+    // need converting. --fail-on-empty looks at every release, so it still needs them.
     if opts.diff
-        && let Some(latest) = releases.first()
+        && !opts.fail_on_empty
+        && let DiffBy::Timestamp = opts.diff_by
+        && let Some(updated) = feed.updated
+        && let Some(last) =
+            find_cache_file(opts, "last_release").and_then(|x| read_last_release(&x))
+        && updated <= last
+    {
+        tracing::info!(%last, "feed not updated since the last --diff run");
+        return Ok(Vec::new());
+    }
+    parse_releases(feed, opts)
+}
+
+/// A progress bar on stderr for `len` steps, or a spinner if the length isn't known. It's hidden
+/// unless both stdout and stderr are terminals, and with --quiet or -v where it would get in the
+/// way of the logs.
+fn progress_bar(opts: &Cli, len: Option<u64>, message: &'static str) -> ProgressBar {
+    if opts.quiet
+        || opts.verbose > 0
+        || cfg!(target_arch = "wasm32")
+        || !std::io::stdout().is_terminal()
+        || !std::io::stderr().is_terminal()
+    {
+        return ProgressBar::hidden();
+    }
+    let progress = match len {
+        Some(len) => ProgressBar::new(len).with_style(
+            ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len}")
+                .unwrap()
+                .progress_chars("=> "),
+        ),
+        None => {
+            let progress = ProgressBar::new_spinner();
+            progress.enable_steady_tick(std::time::Duration::from_millis(100));
+            progress
+        }
+    };
+    progress.with_message(message)
+}
+
+/// Fetches releases, exiting with the usual status for SIGINT if Ctrl-C is pressed first so
+/// nothing after the fetch, like writing the --diff cache, happens.
+#[cfg(not(target_arch = "wasm32"))]
+async fn get_releases_or_cancel(
+    client: &reqwest::Client,
+    opts: &Cli,
+) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    tokio::select! {
+        releases = get_releases(client, opts) => releases,
+        Ok(()) = tokio::signal::ctrl_c() => {
+            eprintln!("cancelled");
+            std::process::exit(130);
+        }
+    }
+}
+
+/// There are no signals on wasm32.
+#[cfg(target_arch = "wasm32")]
+async fn get_releases_or_cancel(
+    client: &reqwest::Client,
+    opts: &Cli,
+) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    get_releases(client, opts).await
+}
+
+/// Releases in `new` whose entry id isn't in `old`, for the diff command.
+fn diff_snapshots(
+    old: &PathBuf,
+    new: &PathBuf,
+    opts: &Cli,
+) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    let read = |path: &PathBuf| -> Result<_, Box<dyn std::error::Error>> {
+        let feed = parse_feed(&std::fs::read(path)?, opts.lenient)
+            .map_err(|err| format!("failed to parse {}: {}", path.display(), err))?;
+        parse_releases(feed, opts)
+    };
+    let old = read(old)?;
+    let mut releases = read(new)?;
+    // Checked before the old releases are removed, as only new ones are left after.
+    if opts.fail_on_empty && releases.is_empty() {
+        return Err(NO_RELEASES.into());
+    }
+    releases.retain(|x| !old.iter().any(|y| y.id == x.id));
+    Ok(releases)
+}
+
+/// The link to an entry's blog post, which Atom marks as the alternate link.
+fn release_link(links: &[feed_rs::model::Link]) -> Option<String> {
+    links
+        .iter()
+        .find(|x| x.rel.as_deref() == Some("alternate"))
+        .or(links.first())
+        .map(|x| x.href.clone())
+}
+
+fn parse_releases(
+    feed: feed_rs::model::Feed,
+    opts: &Cli,
+) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    let matching = feed
+        .entries
+        .iter()
+        .filter(|x| is_chromeos_entry(x, opts))
+        .count();
+    if !feed.entries.is_empty() && matching == 0 {
+        tracing::warn!(
+            entries = feed.entries.len(),
+            "no feed entries are ChromeOS releases, the feed's categories may have changed"
+        );
+    }
+    let progress = progress_bar(opts, Some(matching as u64), "converting releases");
+    let releases = feed
+        .entries
+        .into_iter()
+        .filter(|x| is_chromeos_entry(x, opts))
+        .inspect(|_| progress.inc(1))
+        .filter_map(|x| {
+            let categories = x.categories.into_iter().map(|x| x.term).collect();
+            let link = release_link(&x.links);
+            let author = x
+                .authors
+                .into_iter()
+                .next()
+                .map(|x| x.name)
+                .filter(|x| !x.is_empty());
+            // Some entries only have a summary, which holds the post instead.
+            let body = match (x.content, x.summary) {
+                (Some(content), summary) => content.body.or(summary.map(|x| x.content)),
+                (None, Some(summary)) => Some(summary.content),
+                (None, None) => return None,
+            };
+            Some((x.id, categories, link, author, x.title?, body, x.updated?))
+        })
+        .map(|(id, categories, link, author, title, body, updated)| {
+            let source_hash = blake3::hash(body.as_deref().unwrap_or("").as_bytes())
+                .to_hex()
+                .to_string();
+            let board_versions = match &body {
+                Some(body) if opts.parse_boards => parse_board_versions(body),
+                _ => Vec::new(),
+            };
+            let parsed = render_content(body, opts);
+            let title = if opts.no_entity_decode {
+                title.content
+            } else {
+                decode_entities(&title.content).into_owned()
+            };
+
+            let bug_ids = parse_bug_ids(&parsed);
+
+            let mut should_filter = !opts.unfiltered;
+
+            let mut lines: Vec<&str> = parsed.split('\n').collect();
+            let mut footer: Vec<&str> = Vec::new();
+            if should_filter {
+                lines.dedup();
+                if let Some(start) = footer_start(&lines, &opts.footer_phrases) {
+                    footer = lines.split_off(start);
+                }
+            }
+
+            let mut filtered: Vec<Cow<str>> = Vec::new();
+            let mut summary = String::new();
+            let annotate = |line: &str| Cow::from(format!("[filtered] {}", line));
+
+            for line in lines.iter() {
+                if should_filter {
+                    if is_announcement(line) {
+                        let (formatted, rest) = match line.split_once("Want to know") {
+                            Some((formatted, rest)) => (formatted.trim(), Some(rest)),
+                            None => (line.trim(), None),
+                        };
+                        summary = formatted.into();
+                        filtered.push(formatted.into());
+                        if opts.show_filtered
+                            && let Some(rest) = rest
+                        {
+                            filtered.push(annotate(&format!("Want to know{}", rest)));
+                        }
+                    } else if line.contains("See the latest release")
+                        || line.contains("Release notes for")
+                    {
+                        filtered.push((*line).into());
+                    } else if is_security_heading(line) {
+                        filtered.push("".into());
+                        filtered.push((*line).into());
+                        should_filter = false;
+                    } else if opts.show_filtered {
+                        filtered.push(annotate(line));
+                    }
+                } else {
+                    filtered.push((*line).into());
+                }
+            }
+            if opts.show_filtered {
+                filtered.extend(footer.iter().map(|x| annotate(x)));
+            }
+            if summary.is_empty()
+                && let Some(line) = lines.iter().map(|x| x.trim()).find(|x| !x.is_empty())
+            {
+                summary = truncate_summary(line, opts.summary_length);
+            }
+            let (chrome_versions, platform_versions) = parse_versions(&filtered.join("\n"));
+            let chrome_version = chrome_versions.first().cloned();
+            let platform_version = platform_versions.first().cloned();
+            let announcement = lines
+                .iter()
+                .find(|x| is_announcement(x))
+                .copied()
+                .unwrap_or("");
+            let (is_lts, lts_milestone) = parse_lts(announcement);
+            let channel = parse_channel(announcement, is_lts);
+            let security_fixes = lines.iter().any(|x| is_security_heading(x));
+            let milestone = lts_milestone.or_else(|| {
+                chrome_version
+                    .as_ref()
+                    .and_then(|x| x.split('.').next()?.parse().ok())
+            });
+            let content = filtered.join("\n");
+            let footer = (!footer.is_empty()).then(|| footer.join("\n").trim().to_string());
+            Release {
+                id,
+                categories,
+                link,
+                author,
+                title,
+                summary,
+                content,
+                footer,
+                timestamp: updated,
+                bug_ids,
+                chrome_version,
+                platform_version,
+                chrome_versions,
+                platform_versions,
+                previous_version: None,
+                channel,
+                milestone,
+                is_lts,
+                security_fixes,
+                board_versions,
+                rereleased: false,
+                is_new: true,
+                score: None,
+                source_hash,
+            }
+        })
+        .collect();
+    progress.finish_and_clear();
+    Ok(releases)
+}
+
+/// Returns the path to a file in the cache directory, creating the directory if needed.
+fn place_cache_file(opts: &Cli, name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(dir) = &opts.cache_dir {
+        std::fs::create_dir_all(dir)?;
+        return Ok(dir.join(name));
+    }
+    #[cfg(all(feature = "desktop", unix))]
+    {
+        let xdg = xdg::BaseDirectories::with_prefix("crosreleasenotifier")?;
+        Ok(xdg.place_cache_file(name)?)
+    }
+    #[cfg(not(all(feature = "desktop", unix)))]
+    Err(format!("no cache directory for {}, pass --cache-dir", name).into())
+}
+
+/// Returns the path to a file in the cache directory if it exists, without creating anything.
+fn find_cache_file(opts: &Cli, name: &str) -> Option<PathBuf> {
+    if let Some(dir) = &opts.cache_dir {
+        let path = dir.join(name);
+        return path.exists().then_some(path);
+    }
+    #[cfg(all(feature = "desktop", unix))]
+    {
+        xdg::BaseDirectories::with_prefix("crosreleasenotifier")
+            .ok()?
+            .find_cache_file(name)
+    }
+    #[cfg(not(all(feature = "desktop", unix)))]
+    None
+}
+
+/// Reads the timestamp of the newest release seen by the last --diff run.
+fn read_last_release(path: &PathBuf) -> Option<DateTime<Utc>> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+/// The releases stored by --cache-releases.
+#[derive(Serialize, Deserialize)]
+struct CachedReleases {
+    /// Hash of the options the releases were fetched and converted with.
+    options: String,
+    releases: Vec<Release>,
+    /// The source hash of each release, which isn't serialized with it.
+    source_hashes: Vec<String>,
+}
+
+/// Hashes the options that change which releases are fetched or how they're converted, so
+/// cached releases aren't shown under different ones.
+fn release_options_hash(opts: &Cli) -> String {
+    let options = format!(
+        "{:?}",
+        (
+            (
+                build_feed_url(opts),
+                &opts.feed_mirrors,
+                opts.releases,
+                opts.start,
+                opts.paginate
+            ),
+            (opts.published_min, opts.published_max),
+            (
+                &opts.categories,
+                &opts.flex_categories,
+                opts.category_contains,
+                opts.no_category_filter
+            ),
+            (opts.flex_only, opts.no_flex),
+            (
+                opts.decorator(),
+                opts.gfm,
+                opts.underline_as,
+                opts.no_entity_decode,
+                opts.width
+            ),
+            (opts.unfiltered, opts.show_filtered, &opts.footer_phrases),
+            (opts.parse_boards, opts.summary_length),
+        )
+    );
+    blake3::hash(options.as_bytes()).to_hex().to_string()
+}
+
+fn write_cached_releases(
+    opts: &Cli,
+    releases: &[Release],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cached = CachedReleases {
+        options: release_options_hash(opts),
+        releases: releases.to_vec(),
+        source_hashes: releases.iter().map(|x| x.source_hash.clone()).collect(),
+    };
+    let path = place_cache_file(opts, "releases")?;
+    write_atomic(&path, &serde_json::to_vec(&cached)?)?;
+    Ok(())
+}
+
+fn read_cached_releases(opts: &Cli) -> Result<Vec<Release>, Box<dyn std::error::Error>> {
+    let path = find_cache_file(opts, "releases")
+        .ok_or("no cached releases, run with --cache-releases first")?;
+    let cached: CachedReleases = serde_json::from_slice(&std::fs::read(&path)?)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    if cached.options != release_options_hash(opts) {
+        return Err(
+            "the cached releases were fetched with different options, fetch them again with \
+            --cache-releases"
+                .into(),
+        );
+    }
+    let mut releases = cached.releases;
+    for (release, hash) in releases.iter_mut().zip(cached.source_hashes) {
+        release.source_hash = hash;
+    }
+    Ok(releases)
+}
+
+/// A post announcing a version, as remembered by --diff.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Announcement {
+    id: String,
+    hash: String,
+}
+
+/// Versions seen so far mapped to each distinct announcement of them. Edits of a post are kept
+/// as announcements with the same id.
+type VersionHistory = HashMap<String, Vec<Announcement>>;
+
+/// How many entry ids or content hashes --diff-by id and content-hash remember.
+const MAX_SEEN_IDS: usize = 500;
+
+/// The key --diff-by id or content-hash remembers a release by.
+fn diff_key(release: &Release, diff_by: DiffBy) -> String {
+    match diff_by {
+        DiffBy::Timestamp | DiffBy::Id => release.id.clone(),
+        // Whitespace is collapsed so that wrapping the content differently doesn't change it.
+        DiffBy::ContentHash => {
+            let content = release
+                .content
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            blake3::hash(content.as_bytes()).to_hex().to_string()
+        }
+    }
+}
+
+/// Keys for the releases seen by the caches of the other --diff-by options, for a first run of
+/// --diff-by id or content-hash. Returns `None` if none of them exist.
+fn migrate_seen(opts: &Cli, releases: &[Release], diff_by: DiffBy) -> Option<Vec<String>> {
+    let ids = match diff_by {
+        DiffBy::ContentHash => find_cache_file(opts, "seen_ids"),
+        DiffBy::Timestamp | DiffBy::Id => None,
+    };
+    let seen: Vec<&Release> = if let Some(path) = ids {
+        let ids: HashSet<String> = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+        releases.iter().filter(|x| ids.contains(&x.id)).collect()
+    } else {
+        let last = read_last_release(&find_cache_file(opts, "last_release")?)?;
+        releases.iter().filter(|x| x.timestamp <= last).collect()
+    };
+    tracing::info!(
+        releases = seen.len(),
+        ?diff_by,
+        "carrying the releases seen by --diff over"
+    );
+    Some(seen.into_iter().map(|x| diff_key(x, diff_by)).collect())
+}
+
+/// Removes the files --diff remembers releases with, for --diff-reset.
+fn reset_diff(opts: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    for name in ["last_release", "seen_ids", "seen_hashes", "versions"] {
+        if let Some(path) = find_cache_file(opts, name) {
+            tracing::info!(path = %path.display(), "removing the --diff cache");
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces a file by writing a temporary file next to it and renaming it into place, so an
+/// interrupted run leaves either the old or the new contents rather than a truncated file.
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.to_path_buf().into_os_string();
+    temp.push(".tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+/// Appends releases to a JSON lines archive. With a maximum, the oldest lines are dropped and
+/// the file is replaced with `write_atomic`.
+fn append_history(
+    path: &PathBuf,
+    releases: &[Release],
+    max: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut emitted: Vec<&Release> = releases.iter().collect();
+    emitted.sort_by_key(|x| x.timestamp);
+    let mut lines = Vec::new();
+    for release in emitted {
+        lines.push(serde_json::to_string(release)?);
+    }
+    match max {
+        None => {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            for line in lines {
+                writeln!(file, "{}", line)?;
+            }
+        }
+        Some(max) => {
+            let existing = match std::fs::read_to_string(path) {
+                Ok(x) => x,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(err) => return Err(err.into()),
+            };
+            let mut all: Vec<&str> = existing.lines().filter(|x| !x.is_empty()).collect();
+            all.extend(lines.iter().map(String::as_str));
+            let all = &all[all.len().saturating_sub(max)..];
+            let mut output = all.join("\n");
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            write_atomic(path, output.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Flags releases whose version was announced before by another post, adding anything new to
+/// the history. An edited post announces the same version again under the same id, so it isn't
+/// flagged. Releases are visited oldest first so the later announcement is the one flagged.
+fn mark_rereleases(releases: &mut [Release], history: &mut VersionHistory) {
+    let mut oldest_first: Vec<&mut Release> = releases.iter_mut().collect();
+    oldest_first.sort_by(|x, y| newest_first(y, x));
+    for release in oldest_first {
+        let Some(version) = release.version() else {
+            continue;
+        };
+        let announcement = Announcement {
+            id: release.id.clone(),
+            hash: release.source_hash.clone(),
+        };
+        let seen = history.entry(version.to_string()).or_default();
+        if !seen.contains(&announcement) {
+            release.rereleased = seen.iter().any(|x| x.id != release.id);
+            seen.push(announcement);
+        }
+    }
+}
+
+/// Width to wrap release content at in a format. Only the pretty format is wrapped, and only
+/// with --width, so the formats for machines always get the content as it was parsed.
+fn output_width(opts: &Cli, format: OutputFormat) -> usize {
+    match (format, opts.width) {
+        #[cfg(not(target_arch = "wasm32"))]
+        (OutputFormat::Pretty, Some(0)) => terminal_size::terminal_size()
+            .map(|(width, _)| width.0 as usize)
+            .unwrap_or(usize::MAX),
+        // There's no terminal to measure on wasm32.
+        #[cfg(target_arch = "wasm32")]
+        (OutputFormat::Pretty, Some(0)) => usize::MAX,
+        (OutputFormat::Pretty, Some(width)) => width,
+        _ => usize::MAX,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A duration in its largest whole unit up to weeks, e.g. `3 days`.
+fn humanize_duration(d: chrono::Duration) -> String {
+    let (count, unit) = if d.num_minutes() < 1 {
+        (d.num_seconds().max(0), "second")
+    } else if d.num_hours() < 1 {
+        (d.num_minutes(), "minute")
+    } else if d.num_days() < 1 {
+        (d.num_hours(), "hour")
+    } else if d.num_weeks() < 1 {
+        (d.num_days(), "day")
+    } else {
+        (d.num_weeks(), "week")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// When a release was released in the pretty format, relative to now with --relative-time.
+fn released_at(release: &Release, opts: &Cli) -> String {
+    let date = opts.timezone.format(release.timestamp, "%d/%m/%Y %H:%M");
+    if opts.relative_time {
+        let ago = humanize_duration(Utc::now() - release.timestamp);
+        format!("{} ago ({})", ago, date)
+    } else {
+        format!("at {}", date)
+    }
+}
+
+fn format_pretty(release: &Release, opts: &Cli, anchor: Option<&str>) -> String {
+    let content = wrap_text(&release.content, output_width(opts, OutputFormat::Pretty));
+    let emoji = match release.channel {
+        Some(channel) if opts.emoji => format!("{} ", channel.emoji()),
+        _ => "".into(),
+    };
+    if let Some(anchor) = anchor {
+        let level = match opts.group_by {
+            Some(_) => "###",
+            None => "##",
+        };
+        let mut header = format!(
+            "{} {}{} {{#{}}}\n\n{} {}",
+            level,
+            emoji,
+            release.title,
+            anchor,
+            if release.rereleased {
+                "Re-released"
+            } else {
+                "Released"
+            },
+            released_at(release, opts)
+        );
+        if let Some(versions) = release.version_line() {
+            header += &format!("\n\n{}", versions);
+        }
+        if opts.verbose > 0
+            && let Some(score) = release.score
+        {
+            header += &format!("\n\nSearch score {}", score);
+        }
+        return format!("{}\n\n{}\n", header, content.trim_end());
+    }
+    if opts.collapsible {
+        let date = opts.timezone.format(release.timestamp, "%d/%m/%Y");
+        let summary = match release.version() {
+            Some(version) => format!("{}{} ({}, {})", emoji, release.title, version, date),
+            None => format!("{}{} ({})", emoji, release.title, date),
+        };
+        return format!(
+            "<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n",
+            escape_html(&summary),
+            content.trim_end()
+        );
+    }
+    let mut header = format!(
+        "============\n{}{}\n{} {}",
+        emoji,
+        release.title,
+        if release.rereleased {
+            "Re-released"
+        } else {
+            "Released"
+        },
+        released_at(release, opts)
+    );
+    if let Some(versions) = release.version_line() {
+        header += &format!("\n{}", versions);
+    }
+    if opts.verbose > 0
+        && let Some(score) = release.score
+    {
+        header += &format!("\nSearch score {}", score);
+    }
+    format!("{}\n============\n{}", header, content)
+}
+
+/// CVE ids such as `CVE-2024-0517` mentioned in the content, in order of first appearance.
+fn parse_cves(content: &str) -> Vec<&str> {
+    static CVE: OnceLock<Regex> = OnceLock::new();
+    let cve = CVE.get_or_init(|| Regex::new(r"\bCVE-\d{4}-\d{4,}\b").unwrap());
+    let mut cves: Vec<&str> = Vec::new();
+    for x in cve.find_iter(content) {
+        if !cves.contains(&x.as_str()) {
+            cves.push(x.as_str());
+        }
+    }
+    cves
+}
+
+/// Formats releases as the notes of a GitHub release: a section per release headed by its
+/// version and date, then the CVEs fixed by the security updates among them.
+fn format_github_release(releases: &[Release]) -> String {
+    let heading = |x: &Release| {
+        format!(
+            "{} ({})",
+            x.version().unwrap_or(&x.title),
+            x.timestamp.format("%Y-%m-%d")
+        )
+    };
+    let mut output = String::from("## What's new\n");
+    for release in releases {
+        output += &format!("\n### {}\n\n{}\n", heading(release), release.content.trim());
+    }
+    let security: Vec<&Release> = releases.iter().filter(|x| x.security_fixes).collect();
+    if !security.is_empty() {
+        output += "\n## Security\n\n";
+        for release in security {
+            let cves = parse_cves(&release.content);
+            if cves.is_empty() {
+                output += &format!("- {}\n", heading(release));
+            } else {
+                output += &format!("- {}: {}\n", heading(release), cves.join(", "));
+            }
+        }
+    }
+    output
+}
+
+/// Formats releases as an RSS 2.0 feed, with the entry ids as guids so readers recognize the
+/// items across runs.
+fn format_rss(releases: &[Release]) -> String {
+    let mut output = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<rss version=\"2.0\">\n<channel>\n",
+        "<title>ChromeOS Releases</title>\n",
+        "<link>https://chromereleases.googleblog.com/</link>\n",
+        "<description>ChromeOS releases from the Chrome Releases blog.</description>\n",
+    ));
+    if let Some(newest) = releases.iter().map(|x| x.timestamp).max() {
+        output += &format!("<lastBuildDate>{}</lastBuildDate>\n", newest.to_rfc2822());
+    }
+    for release in releases {
+        output += "<item>\n";
+        output += &format!("<title>{}</title>\n", escape_html(&release.title));
+        if let Some(link) = &release.link {
+            output += &format!("<link>{}</link>\n", escape_html(link));
+        }
+        output += &format!(
+            "<description>{}</description>\n",
+            escape_html(release.content.trim())
+        );
+        output += &format!("<pubDate>{}</pubDate>\n", release.timestamp.to_rfc2822());
+        output += &format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n",
+            escape_html(&release.id)
+        );
+        output += "</item>\n";
+    }
+    output + "</channel>\n</rss>\n"
+}
+
+/// The error --fail-on-empty exits with.
+const NO_RELEASES: &str = "the feed has no ChromeOS releases, its categories may have changed";
+
+/// The status --exit-code exits with when --diff finds new releases.
+const NEW_RELEASES_EXIT_CODE: i32 = 10;
+
+/// Fields --template can use.
+const TEMPLATE_FIELDS: [&str; 4] = ["title", "summary", "content", "timestamp"];
+
+/// Renders a release with a --template, replacing each `{field}` with the release's field. The
+/// escapes `\n`, `\t` and `\\` are interpreted too, as shells don't do that in arguments.
+fn render_template(release: &Release, template: &str) -> Result<String, String> {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}', '\\']) {
+        output += &rest[..i];
+        rest = &rest[i..];
+        if let Some(x) = rest.strip_prefix('\\') {
+            let (escaped, x) = match x.chars().next() {
+                Some('n') => ('\n', &x[1..]),
+                Some('t') => ('\t', &x[1..]),
+                Some('\\') => ('\\', &x[1..]),
+                // Backslashes before anything else are kept as they are.
+                _ => ('\\', x),
+            };
+            output.push(escaped);
+            rest = x;
+            continue;
+        }
+        if let Some(x) = rest.strip_prefix("{{") {
+            output.push('{');
+            rest = x;
+            continue;
+        }
+        if let Some(x) = rest.strip_prefix("}}") {
+            output.push('}');
+            rest = x;
+            continue;
+        }
+        if rest.starts_with('}') {
+            return Err("unmatched `}` in the template, write `}}` for a literal brace".into());
+        }
+        let Some(end) = rest.find('}') else {
+            return Err("unclosed `{` in the template, write `{{` for a literal brace".into());
+        };
+        let placeholder = &rest[1..end];
+        rest = &rest[end + 1..];
+        match placeholder.split_once(':') {
+            None if placeholder == "title" => output += &release.title,
+            None if placeholder == "summary" => output += &release.summary,
+            None if placeholder == "content" => output += &release.content,
+            None if placeholder == "timestamp" => output += &release.timestamp.to_rfc3339(),
+            Some(("timestamp", format)) => {
+                if chrono::format::StrftimeItems::new(format)
+                    .any(|x| matches!(x, chrono::format::Item::Error))
+                {
+                    return Err(format!(
+                        "invalid timestamp format `{}` in the template",
+                        format
+                    ));
+                }
+                output += &release.timestamp.format(format).to_string();
+            }
+            _ => {
+                return Err(format!(
+                    "unknown template field `{}`, expected one of {}",
+                    placeholder,
+                    TEMPLATE_FIELDS.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(output + rest)
+}
+
+/// Anchors for the markdown headings of releases by id, e.g. `m120-stable-2024-01-15`, with a
+/// numbered suffix for releases that would share one. Parts that weren't parsed are left out.
+fn release_anchors(releases: &[Release]) -> HashMap<&str, String> {
+    let mut anchors = HashMap::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for release in releases {
+        let mut parts = Vec::new();
+        if let Some(milestone) = release.milestone {
+            parts.push(format!("m{}", milestone));
+        }
+        if let Some(channel) = release.channel {
+            parts.push(channel.name().to_lowercase());
+        }
+        parts.push(release.timestamp.format("%Y-%m-%d").to_string());
+        let anchor = parts.join("-");
+        let count = counts.entry(anchor.clone()).or_insert(0);
+        *count += 1;
+        let anchor = match *count {
+            1 => anchor,
+            n => format!("{}-{}", anchor, n),
+        };
+        anchors.insert(release.id.as_str(), anchor);
+    }
+    anchors
+}
+
+/// Orders releases newest first. The id breaks ties between releases updated at the same time,
+/// so their order doesn't depend on the feed's.
+fn newest_first(x: &Release, y: &Release) -> std::cmp::Ordering {
+    y.timestamp.cmp(&x.timestamp).then_with(|| x.id.cmp(&y.id))
+}
+
+/// Sets the previous version of each release to the version of the next older release on the
+/// same channel. This runs before any filtering so it's known for every release in the fetched
+/// window except the oldest of each channel.
+fn link_previous_versions(releases: &mut [Release]) {
+    let mut order: Vec<usize> = (0..releases.len()).collect();
+    order.sort_by(|&x, &y| newest_first(&releases[x], &releases[y]));
+    let previous: Vec<Option<String>> = order
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let channel = releases[x].channel?;
+            order[i + 1..]
+                .iter()
+                .map(|&x| &releases[x])
+                .find(|x| x.channel == Some(channel))
+                .and_then(|x| x.version())
+                .map(String::from)
+        })
+        .collect();
+    for (x, previous) in order.into_iter().zip(previous) {
+        releases[x].previous_version = previous;
+    }
+}
+
+/// Keeps the newest `limit` releases of each channel, in their current order.
+fn limit_per_channel(releases: &mut Vec<Release>, limit: usize) {
+    let mut newest: Vec<&Release> = releases.iter().collect();
+    newest.sort_by_key(|x| Reverse(x.timestamp));
+    let mut counts: HashMap<Option<Channel>, usize> = HashMap::new();
+    let mut kept: Vec<String> = Vec::new();
+    for release in newest {
+        let count = counts.entry(release.channel).or_insert(0);
+        if *count < limit {
+            *count += 1;
+            kept.push(release.id.clone());
+        }
+    }
+    releases.retain(|x| kept.contains(&x.id));
+}
+
+/// Replaces multiple Canary releases with one, in place of the newest, whose content lists the
+/// announcement of each.
+fn summarize_canary(releases: &[Release]) -> Vec<Release> {
+    let is_canary = |x: &Release| x.channel == Some(Channel::Canary);
+    let canary: Vec<&Release> = releases.iter().filter(|x| is_canary(x)).collect();
+    let Some(newest) = canary.iter().max_by_key(|x| x.timestamp) else {
+        return releases.to_vec();
+    };
+    if canary.len() == 1 {
+        return releases.to_vec();
+    }
+    let mut summarized = (*newest).clone();
+    summarized.summary = format!(
+        "{} Canary releases, the newest: {}",
+        canary.len(),
+        newest.summary
+    );
+    summarized.content = canary
+        .iter()
+        .map(|x| x.summary.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    summarized.bug_ids = Vec::new();
+    for id in canary.iter().flat_map(|x| x.bug_ids.iter()) {
+        if !summarized.bug_ids.contains(id) {
+            summarized.bug_ids.push(id.clone());
+        }
+    }
+    summarized.security_fixes = canary.iter().any(|x| x.security_fixes);
+    releases
+        .iter()
+        .filter_map(|x| {
+            if x.id == newest.id {
+                Some(summarized.clone())
+            } else if is_canary(x) {
+                None
+            } else {
+                Some(x.clone())
+            }
+        })
+        .collect()
+}
+
+/// A milestone's releases as written by the json and yaml formats with --group-by milestone.
+#[derive(Serialize)]
+struct MilestoneGroup<'a> {
+    milestone: Option<u32>,
+    releases: Vec<&'a Release>,
+}
+
+fn milestone_groups(releases: &[Release]) -> Vec<MilestoneGroup<'_>> {
+    group_by_milestone(releases)
+        .into_iter()
+        .map(|(milestone, releases)| MilestoneGroup {
+            milestone,
+            releases,
+        })
+        .collect()
+}
+
+/// Groups releases by milestone, newest first with `None` last, keeping their order within
+/// each group.
+fn group_by_milestone(releases: &[Release]) -> Vec<(Option<u32>, Vec<&Release>)> {
+    let mut groups: Vec<(Option<u32>, Vec<&Release>)> = Vec::new();
+    for release in releases {
+        match groups.iter_mut().find(|(x, _)| *x == release.milestone) {
+            Some((_, group)) => group.push(release),
+            None => groups.push((release.milestone, vec![release])),
+        }
+    }
+    // None sorts before Some, so reversing puts it at the end.
+    groups.sort_by_key(|(x, _)| Reverse(*x));
+    groups
+}
+
+/// Shows a notification, or prints it for --dry-run.
+#[cfg(feature = "desktop")]
+fn show_notification(
+    notification: &notify_rust::Notification,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        println!(
+            "Notification\nApp name: {}\nIcon: {}\nSummary: {}\nBody: {}\nTimeout: {:?}\n",
+            notification.appname,
+            notification.icon,
+            notification.summary,
+            notification.body,
+            notification.timeout
+        );
+    } else {
+        #[cfg(all(windows, feature = "windows-toast"))]
+        {
+            use tauri_winrt_notification::{Duration, IconCrop, Toast};
+            // Toasts can't be shown indefinitely, so Long is the closest to Timeout::Never. They
+            // can only be shown under the app ID of an installed app, so PowerShell's is used and
+            // the app name is left out. Clicking one opens nothing, as the handler would have to
+            // keep the process running after the notification is shown.
+            let mut toast = Toast::new(Toast::POWERSHELL_APP_ID)
+                .title(&notification.summary)
+                .text1(&notification.body)
+                .duration(Duration::Long);
+            // Toasts take an absolute path to an image, there is no icon theme to look names up
+            // in.
+            let icon = std::path::Path::new(&notification.icon);
+            if icon.is_file() {
+                toast = toast.icon(
+                    &std::path::absolute(icon)?,
+                    IconCrop::Square,
+                    &notification.appname,
+                );
+            }
+            toast.show()?;
+        }
+        #[cfg(all(target_os = "macos", feature = "macos-notification-center"))]
+        {
+            // Passed as arguments so the text needs no AppleScript escaping. Notification Center
+            // keeps notifications until dismissed, which matches Timeout::Never.
+            let status = Command::new("osascript")
+                .args([
+                    "-e",
+                    "on run argv",
+                    "-e",
+                    "display notification (item 2 of argv) with title (item 1 of argv)",
+                    "-e",
+                    "end run",
+                    notification.summary.as_str(),
+                    notification.body.as_str(),
+                ])
+                .status()?;
+            if !status.success() {
+                return Err(format!("osascript exited with {}", status).into());
+            }
+        }
+        #[cfg(not(any(
+            all(windows, feature = "windows-toast"),
+            all(target_os = "macos", feature = "macos-notification-center")
+        )))]
+        notification.show()?;
+    }
+    Ok(())
+}
+
+/// The icon to show notifications with, or `None` if it is a path that doesn't exist.
+#[cfg(feature = "desktop")]
+fn notification_icon(icon: &str) -> Option<&str> {
+    if icon.contains(std::path::is_separator) && !std::path::Path::new(icon).exists() {
+        tracing::warn!(icon, "notification icon doesn't exist, showing no icon");
+        None
+    } else {
+        Some(icon)
+    }
+}
+
+/// Joins items into English, e.g. "a, b and c".
+#[cfg(feature = "desktop")]
+fn join_english(items: &[String]) -> String {
+    match items {
+        [] => "".into(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+/// Describes a batch of releases, e.g. "3 new releases across Stable (M120) and Beta (M121),
+/// including 1 security update." Channels and milestones that couldn't be parsed are left out.
+#[cfg(feature = "desktop")]
+fn batch_summary(releases: &[Release], new: bool) -> String {
+    let mut channels: Vec<(Channel, Vec<u32>)> = Vec::new();
+    for release in releases.iter() {
+        let Some(channel) = release.channel else {
+            continue;
+        };
+        let index = match channels.iter().position(|(x, _)| *x == channel) {
+            Some(index) => index,
+            None => {
+                channels.push((channel, Vec::new()));
+                channels.len() - 1
+            }
+        };
+        if let Some(milestone) = release.milestone
+            && !channels[index].1.contains(&milestone)
+        {
+            channels[index].1.push(milestone);
+        }
+    }
+    let mut summary = format!(
+        "{} {}release{}",
+        releases.len(),
+        if new { "new " } else { "" },
+        if releases.len() == 1 { "" } else { "s" }
+    );
+    if !channels.is_empty() {
+        let channels: Vec<String> = channels
+            .iter()
+            .map(|(channel, milestones)| {
+                if milestones.is_empty() {
+                    channel.name().into()
+                } else {
+                    let milestones: Vec<String> =
+                        milestones.iter().map(|x| format!("M{}", x)).collect();
+                    format!("{} ({})", channel.name(), milestones.join(", "))
+                }
+            })
+            .collect();
+        summary += &format!(" across {}", join_english(&channels));
+    }
+    let security = releases.iter().filter(|x| x.security_fixes).count();
+    if security > 0 {
+        summary += &format!(
+            ", including {} security update{}",
+            security,
+            if security == 1 { "" } else { "s" }
+        );
+    }
+    summary + "."
+}
+
+/// Writes a value to stdout as JSON, indented if `pretty`.
+fn write_json<T: Serialize>(value: &T, pretty: bool) -> Result<(), serde_json::Error> {
+    if pretty {
+        serde_json::to_writer_pretty(std::io::stdout(), value)
+    } else {
+        serde_json::to_writer(std::io::stdout(), value)
+    }
+}
+
+/// A release as a Discord embed.
+#[derive(Serialize)]
+struct DiscordEmbed<'a> {
+    title: String,
+    description: String,
+    timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+impl DiscordEmbed<'_> {
+    /// The characters Discord counts towards the limit for the embeds of a message.
+    fn length(&self) -> usize {
+        self.title.chars().count() + self.description.chars().count()
+    }
+}
+
+/// Discord's limits on the embeds in one message.
+const DISCORD_MAX_EMBEDS: usize = 10;
+const DISCORD_MAX_EMBED_LENGTH: usize = 6000;
+
+/// Posts releases to --discord-webhook as embeds, as many in each message as Discord allows, or
+/// prints the messages for --dry-run.
+async fn post_discord(
+    client: &reqwest::Client,
+    releases: &[Release],
+    opts: &Cli,
+    max_length: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_http()?;
+    let webhook = opts.discord_webhook.as_deref().unwrap_or_default();
+    let embeds = releases.iter().map(|x| DiscordEmbed {
+        title: truncate_content(&x.title, 256, None),
+        description: match max_length {
+            Some(length) => truncate_content(&x.summary, length, x.link.as_deref()),
+            None => x.summary.clone(),
+        },
+        timestamp: x.timestamp,
+        url: x.link.as_deref(),
+    });
+    let mut messages: Vec<Vec<DiscordEmbed>> = Vec::new();
+    for embed in embeds {
+        match messages.last_mut() {
+            Some(message)
+                if message.len() < DISCORD_MAX_EMBEDS
+                    && message.iter().map(DiscordEmbed::length).sum::<usize>() + embed.length()
+                        <= DISCORD_MAX_EMBED_LENGTH =>
+            {
+                message.push(embed)
+            }
+            _ => messages.push(vec![embed]),
+        }
+    }
+    for embeds in messages {
+        let message = serde_json::to_string(&serde_json::json!({ "embeds": embeds }))?;
+        if opts.dry_run {
+            println!("Discord message\n{}\n", message);
+            continue;
+        }
+        let response = client
+            .post(webhook)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(message)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Discord responded with {}: {}", status, body).into());
+        }
+        tracing::info!(embeds = embeds.len(), "posted releases to Discord");
+    }
+    Ok(())
+}
+
+/// Prints releases in a format, or shows them as notifications.
+async fn print_releases(
+    client: &reqwest::Client,
+    format: OutputFormat,
+    shown: &[Release],
+    opts: &Cli,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let max_length = opts.max_content_length.or(format.max_content_length());
+    let truncated: Cow<[Release]> = match max_length {
+        Some(length) => Cow::Owned(
+            shown
+                .iter()
+                .map(|x| Release {
+                    content: truncate_content(&x.content, length, x.link.as_deref()),
+                    ..x.clone()
+                })
+                .collect(),
+        ),
+        None => Cow::Borrowed(shown),
+    };
+    let shown = &*truncated;
+    match format {
+        OutputFormat::Json => {
+            match opts.group_by {
+                Some(GroupBy::Milestone) => {
+                    write_json(&milestone_groups(shown), opts.json_pretty)?;
+                }
+                None => write_json(&shown, opts.json_pretty)?,
+            }
+            // Ends the line, so the next format's output starts on its own.
+            println!();
+        }
+        // An empty list is written as `[]`, the same as in the json format.
+        OutputFormat::Yaml => match opts.group_by {
+            Some(GroupBy::Milestone) => {
+                serde_yaml::to_writer(std::io::stdout(), &milestone_groups(shown))?;
+            }
+            None => serde_yaml::to_writer(std::io::stdout(), &shown)?,
+        },
+        OutputFormat::Pretty => {
+            if !shown.is_empty() {
+                let anchors = if opts.anchors {
+                    release_anchors(shown)
+                } else {
+                    HashMap::new()
+                };
+                let anchor = |x: &Release| anchors.get(x.id.as_str()).map(String::as_str);
+                let groups = opts
+                    .group_by
+                    .map(|GroupBy::Milestone| group_by_milestone(shown));
+                let mut output = match &groups {
+                    Some(groups) => groups
+                        .iter()
+                        .map(|(milestone, releases)| {
+                            let name = match milestone {
+                                Some(x) => format!("M{}", x),
+                                None => "Unknown".into(),
+                            };
+                            let releases = releases
+                                .iter()
+                                .map(|x| format_pretty(x, opts, anchor(x)))
+                                .collect::<Vec<_>>()
+                                .join("\n");
+                            format!("## {}\n\n{}", name, releases)
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    None => shown
+                        .iter()
+                        .map(|x| format_pretty(x, opts, anchor(x)))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                if opts.toc {
+                    // Listed in the order the releases are written in, which --group-by changes.
+                    let ordered: Vec<&Release> = match &groups {
+                        Some(groups) => groups.iter().flat_map(|(_, x)| x.clone()).collect(),
+                        None => shown.iter().collect(),
+                    };
+                    let toc = ordered
+                        .into_iter()
+                        .filter_map(|x| Some(format!("- [{}](#{})", x.title, anchor(x)?)))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    output = format!("{}\n\n{}", toc, output);
+                }
+                if opts.pager {
+                    page(&output)?;
+                } else {
+                    println!("{}", output);
+                }
+            }
+        }
+        OutputFormat::GithubRelease => {
+            if !shown.is_empty() {
+                print!("{}", format_github_release(shown));
+            }
+        }
+        OutputFormat::Rss => print!("{}", format_rss(shown)),
+        OutputFormat::Template => {
+            let template = opts.template.as_deref().unwrap_or_default();
+            for release in shown {
+                print!("{}", render_template(release, template)?);
+            }
+        }
+        OutputFormat::Discord => post_discord(client, shown, opts, max_length).await?,
+        #[cfg(feature = "desktop")]
+        OutputFormat::Notification => {
+            let strings = opts.lang.strings();
+            let icon = notification_icon(&opts.notify_icon);
+            let new_notification = || {
+                let mut notification = notify_rust::Notification::new();
+                notification.appname(&opts.notify_appname);
+                if let Some(icon) = icon {
+                    notification.icon(icon);
+                }
+                notification
+            };
+            if opts.notify_batch {
+                if !shown.is_empty() {
+                    let mut notification = new_notification();
+                    notification
+                        .summary(strings.batch)
+                        .body(&batch_summary(shown, opts.diff))
+                        .timeout(notify_rust::Timeout::Never);
+                    show_notification(&notification, opts.dry_run)?;
+                }
+            } else {
+                for (i, release) in shown.iter().enumerate() {
+                    if i > 0 && opts.notify_throttle > 0 && !opts.dry_run {
+                        sleep(std::time::Duration::from_millis(opts.notify_throttle)).await;
+                    }
+                    let mut date = opts.timezone.format(release.timestamp, strings.date_format);
+                    if opts.relative_time {
+                        let ago = humanize_duration(Utc::now() - release.timestamp);
+                        date += &format!(" ({} ago)", ago);
+                    }
+                    let summary = if release.rereleased {
+                        strings.rerelease
+                    } else {
+                        strings.release
+                    }
+                    .replace("{date}", &date);
+                    let mut notification = new_notification();
+                    notification
+                        .summary(&summary)
+                        .body(&match max_length {
+                            Some(length) => {
+                                truncate_content(&release.summary, length, release.link.as_deref())
+                            }
+                            None => release.summary.clone(),
+                        })
+                        .timeout(notify_rust::Timeout::Never);
+                    show_notification(&notification, opts.dry_run)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints text through the user's pager if stdout is a terminal, or directly otherwise.
+fn page(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !std::io::stdout().is_terminal() {
+        println!("{}", text);
+        return Ok(());
+    }
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".into());
+    let mut args = pager.split_whitespace();
+    let Some(program) = args.next() else {
+        println!("{}", text);
+        return Ok(());
+    };
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
     {
-        serde_json::to_writer(std::fs::File::create(diff_file)?, &latest.timestamp)?;
+        Ok(child) => child,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            println!("{}", text);
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closes its end early if it's quit before reading everything.
+        if let Err(err) = writeln!(stdin, "{}", text)
+            && err.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            return Err(err.into());
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg_attr(feature = "desktop", tokio::main)]
+#[cfg_attr(not(feature = "desktop"), tokio::main(flavor = "current_thread"))]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut opts = Cli::parse();
+    resolve_secrets(&mut opts)?;
+    // Instant isn't available on wasm32-unknown-unknown, while chrono reads the time from JS.
+    let start = Utc::now();
+    let level = match (opts.verbose, opts.log_format) {
+        _ if opts.quiet => tracing::Level::ERROR,
+        // JSON logs are for ingesting, so include the info level events for each run.
+        (0, LogFormat::Text) => tracing::Level::WARN,
+        (0 | 1, _) => tracing::Level::INFO,
+        (2, _) => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level);
+    match opts.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+    if opts.collapsible && !matches!(opts.decorator(), Decorator::Markdown) {
+        return Err("--collapsible needs the markdown decorator".into());
+    }
+    if opts.anchors && !matches!(opts.decorator(), Decorator::Markdown) {
+        return Err("--anchors needs the markdown decorator".into());
+    }
+    if opts.gfm && !matches!(opts.decorator(), Decorator::Markdown) {
+        return Err("--gfm needs the markdown decorator".into());
+    }
+    if opts
+        .format
+        .iter()
+        .any(|x| matches!(x, OutputFormat::Template))
+        && opts.template.is_none()
+    {
+        return Err("the template format needs --template".into());
+    }
+    if opts
+        .format
+        .iter()
+        .any(|x| matches!(x, OutputFormat::Discord))
+        && opts.discord_webhook.is_none()
+    {
+        return Err("the discord format needs --discord-webhook or --discord-webhook-file".into());
+    }
+    if let (Some(since), Some(until)) = (opts.since, opts.until)
+        && since >= until
+    {
+        return Err(format!(
+            "--since {} isn't before --until {}, which is exclusive",
+            since, until
+        )
+        .into());
+    }
+    if opts.diff_reset {
+        reset_diff(&opts)?;
+    }
+    let client = http_client(&opts)?;
+    let mut releases = match &opts.command {
+        Some(Commands::Diff { old, new }) => {
+            if opts.diff {
+                return Err("--diff can't be used with the diff command".into());
+            }
+            diff_snapshots(old, new, &opts)?
+        }
+        None if opts.from_cache => read_cached_releases(&opts)?,
+        None => {
+            let releases = get_releases_or_cancel(&client, &opts).await?;
+            // An empty fetch, such as when the feed wasn't updated, keeps the last releases.
+            if opts.cache_releases && !releases.is_empty() {
+                write_cached_releases(&opts, &releases)?;
+            }
+            releases
+        }
+    };
+    let fetched = releases.len();
+    // The diff command has already checked its NEW feed, as only the new releases are left.
+    if opts.fail_on_empty && releases.is_empty() && opts.command.is_none() {
+        return Err(NO_RELEASES.into());
+    }
+    if !opts.no_sort {
+        releases.sort_by(newest_first);
+    }
+    link_previous_versions(&mut releases);
+    let diff_name = match opts.diff_by {
+        DiffBy::Timestamp => "last_release",
+        DiffBy::Id => "seen_ids",
+        DiffBy::ContentHash => "seen_hashes",
+    };
+    let diff_file = if opts.diff {
+        Some(place_cache_file(&opts, diff_name)?)
+    } else {
+        None
+    };
+    let migrated = match &diff_file {
+        Some(diff_file) if !diff_file.exists() && !matches!(opts.diff_by, DiffBy::Timestamp) => {
+            migrate_seen(&opts, &releases, opts.diff_by)
+        }
+        _ => None,
+    };
+    // Releases remembered by the first --diff run without being output.
+    let mut marked: Vec<Release> = Vec::new();
+    if let Some(diff_file) = &diff_file
+        && !diff_file.exists()
+        && migrated.is_none()
+        && let FirstRun::MarkOnly = opts.first_run
+    {
+        tracing::info!(
+            releases = releases.len(),
+            "first --diff run, marking releases as seen without outputting them"
+        );
+        marked = std::mem::take(&mut releases);
+    }
+    let history = if opts.diff {
+        let history_file = place_cache_file(&opts, "versions")?;
+        let mut history: VersionHistory = std::fs::read(&history_file)
+            .ok()
+            .and_then(|x| serde_json::from_slice(&x).ok())
+            .unwrap_or_default();
+        mark_rereleases(&mut releases, &mut history);
+        Some((history_file, history))
+    } else {
+        None
+    };
+    // Without --diff the cache is only read to mark the new releases, not to filter them.
+    let mut seen_ids: Vec<String> = migrated.unwrap_or_default();
+    if let Some(diff_file) = diff_file
+        .clone()
+        .or_else(|| find_cache_file(&opts, diff_name))
+    {
+        match opts.diff_by {
+            DiffBy::Timestamp => {
+                if let Some(diff_date) = read_last_release(&diff_file) {
+                    tracing::info!(timestamp = %diff_date, "loaded the last --diff timestamp");
+                    for release in releases.iter_mut() {
+                        release.is_new = release.timestamp > diff_date;
+                    }
+                }
+            }
+            DiffBy::Id | DiffBy::ContentHash => {
+                if let Some(seen) = std::fs::read(diff_file)
+                    .ok()
+                    .and_then(|x| serde_json::from_slice(&x).ok())
+                {
+                    seen_ids = seen;
+                }
+                tracing::info!(ids = seen_ids.len(), "loaded the ids seen by --diff");
+            }
+        }
+    }
+    if matches!(opts.diff_by, DiffBy::Id | DiffBy::ContentHash) && !seen_ids.is_empty() {
+        let seen: HashSet<&String> = seen_ids.iter().collect();
+        for release in releases.iter_mut() {
+            release.is_new = !seen.contains(&diff_key(release, opts.diff_by));
+        }
+    }
+    if opts.diff {
+        let before = releases.len();
+        releases.retain(|x| x.is_new);
+        tracing::info!(
+            seen = before - releases.len(),
+            new = releases.len(),
+            "filtered out the releases seen by the last --diff run"
+        );
+    }
+    if let Some(since) = opts.since {
+        releases.retain(|x| x.timestamp >= since);
+    }
+    if let Some(until) = opts.until {
+        releases.retain(|x| x.timestamp < until);
+    }
+    if let Some(channel) = opts.channel.channel() {
+        releases.retain(|x| x.channel == Some(channel));
+    }
+    if opts.no_canary {
+        releases.retain(|x| x.channel != Some(Channel::Canary));
+    }
+    filter_versions(&mut releases, &opts);
+    if let Some(query) = &opts.search {
+        let matcher = SkimMatcherV2::default();
+        for release in releases.iter_mut() {
+            release.score = std::iter::once(release.title.as_str())
+                .chain(release.content.split('\n'))
+                .filter_map(|x| matcher.fuzzy_match(x, query))
+                .max();
+        }
+        releases.retain(|x| x.score.is_some());
+        if let SortOrder::Relevance = opts.sort {
+            releases.sort_by_key(|x| Reverse(x.score));
+        }
+    }
+    if let Some(limit) = opts.limit_per_channel {
+        limit_per_channel(&mut releases, limit);
+    }
+    tracing::info!(
+        duration_ms = (Utc::now() - start).num_milliseconds(),
+        releases = fetched,
+        new_releases = releases.len(),
+        "finished fetching releases"
+    );
+    // Collapsing only changes what's shown, --diff and --history still record every release.
+    let shown: Cow<[Release]> = if opts.canary_summary {
+        Cow::Owned(summarize_canary(&releases))
+    } else {
+        Cow::Borrowed(&releases)
+    };
+    if opts.count_only {
+        match opts.format[0] {
+            OutputFormat::Json => println!("{}", serde_json::json!({ "count": shown.len() })),
+            _ => println!("{}", shown.len()),
+        }
+        return Ok(());
+    }
+    // Every format is tried even if another fails, but the caches are only updated if all of
+    // them succeed so no release is missed.
+    let mut errors = Vec::new();
+    for &format in opts.format.iter() {
+        if opts.quiet && !format.has_side_effects() {
+            continue;
+        }
+        if let Err(err) = print_releases(&client, format, &shown, &opts).await {
+            let name = format.to_possible_value().map(|x| x.get_name().to_string());
+            errors.push(format!("{}: {}", name.unwrap_or_default(), err));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!("failed to output releases as:\n{}", errors.join("\n")).into());
+    }
+    if opts.dry_run && opts.format.iter().any(|x| x.has_side_effects()) {
+        return Ok(());
+    }
+    if let Some(diff_file) = diff_file {
+        match opts.diff_by {
+            DiffBy::Timestamp => {
+                if let Some(latest) = releases.iter().chain(&marked).map(|x| x.timestamp).max() {
+                    tracing::info!(timestamp = %latest, "writing the --diff timestamp");
+                    write_atomic(&diff_file, &serde_json::to_vec(&latest)?)?;
+                }
+            }
+            DiffBy::Id | DiffBy::ContentHash => {
+                // Oldest first, so the ids dropped to stay under the limit are the oldest ones.
+                let mut emitted: Vec<&Release> = releases.iter().chain(&marked).collect();
+                emitted.sort_by_key(|x| x.timestamp);
+                let mut seen: HashSet<String> = seen_ids.iter().cloned().collect();
+                for release in emitted {
+                    let key = diff_key(release, opts.diff_by);
+                    if seen.insert(key.clone()) {
+                        seen_ids.push(key);
+                    }
+                }
+                seen_ids.drain(..seen_ids.len().saturating_sub(MAX_SEEN_IDS));
+                tracing::info!(ids = seen_ids.len(), "writing the ids seen by --diff");
+                write_atomic(&diff_file, &serde_json::to_vec(&seen_ids)?)?;
+            }
+        }
+    }
+    if let Some(path) = &opts.history {
+        append_history(path, &releases, opts.history_max)?;
+    }
+    if let Some((history_file, history)) = history {
+        write_atomic(&history_file, &serde_json::to_vec(&history)?)?;
+    }
+    if opts.exit_code && !releases.is_empty() {
+        std::process::exit(NEW_RELEASES_EXIT_CODE);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::parse_from(std::iter::once("crosreleasenotifier").chain(args.iter().copied()))
+    }
+
+    /// A feed with the entries, given as the XML inside `<feed>`.
+    fn feed(entries: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom"><id>feed</id><title>Chrome Releases</title><updated>2024-01-02T00:00:00Z</updated>{}</feed>"#,
+            entries
+        )
+    }
+
+    /// Parses feed entries, given as the XML inside `<feed>`, the way the CLI would with `args`.
+    fn releases(entries: &str, args: &[&str]) -> Vec<Release> {
+        let feed = feed(entries);
+        parse_releases(parse_feed(feed.as_bytes(), false).unwrap(), &cli(args)).unwrap()
+    }
+
+    fn release() -> Release {
+        releases(
+            r#"<entry><id>e1</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T03:04:05Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;The Stable channel is being updated to 120.0.6099.235.&lt;/p&gt;</content></entry>"#,
+            &[],
+        )
+        .remove(0)
+    }
+
+    /// Serves one canned response per connection on a local port, in order, returning the
+    /// server's URL. Redirects point back to the same URL.
+    fn serve(responses: Vec<(u16, String)>) -> String {
+        use std::io::{BufRead, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = std::io::BufReader::new(stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let location = if (300..400).contains(&status) {
+                    "Location: /feed\r\n"
+                } else {
+                    ""
+                };
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {} Status\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    location,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    const FEED: &str = r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom"><id>feed</id><title>Chrome Releases</title><updated>2024-01-02T00:00:00Z</updated><entry><id>e1</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T00:00:00Z</updated></entry></feed>"#;
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let url = serve(vec![(503, "".into()), (503, "".into()), (200, FEED.into())]);
+        let opts = cli(&[
+            "--feed-url",
+            &url,
+            "--retries",
+            "2",
+            "--retry-delay-ms",
+            "1",
+        ]);
+        let feed = fetch_feed(&http_client(&opts).unwrap(), &opts, 1)
+            .await
+            .unwrap();
+        assert_eq!(feed.entries.len(), 1);
+        assert_eq!(feed.entries[0].id, "e1");
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_retries() {
+        let url = serve(vec![(503, "".into()), (503, "".into()), (503, "".into())]);
+        let opts = cli(&[
+            "--feed-url",
+            &url,
+            "--retries",
+            "2",
+            "--retry-delay-ms",
+            "1",
+        ]);
+        let err = fetch_feed(&http_client(&opts).unwrap(), &opts, 1)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("(503 Service Unavailable)") && err.ends_with("after 3 attempts"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_without_retrying_errors_that_would_repeat() {
+        let url = serve(vec![(302, "".into())]);
+        let opts = cli(&[
+            "--feed-url",
+            &url,
+            "--no-redirects",
+            "--retries",
+            "2",
+            "--retry-delay-ms",
+            "1",
+        ]);
+        let err = fetch_feed(&http_client(&opts).unwrap(), &opts, 1)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("more than 0 redirects"), "{}", err);
+        assert!(!err.contains("attempts"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn streamed_feed_gives_the_same_output_as_a_buffered_one() {
+        let entries: String = (0..2000)
+            .map(|i| {
+                format!(
+                    r#"<entry><id>entry-{i}</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T00:00:00Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;The Stable channel is being updated to 120.0.6099.{i} (Platform Version: 15662.{i}.0) for most ChromeOS devices.&lt;/p&gt;&lt;ul&gt;&lt;li&gt;Fixes &amp;amp; b/{id}&lt;/li&gt;&lt;/ul&gt;</content></entry>"#,
+                    i = i,
+                    id = 300000000 + i
+                )
+            })
+            .collect();
+        let feed = format!(
+            r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom"><id>feed</id><title>Chrome Releases</title><updated>2024-01-02T00:00:00Z</updated>{}</feed>"#,
+            entries
+        );
+        let url = serve(vec![(200, feed.clone())]);
+        let opts = cli(&["--feed-url", &url, "-r", "2000"]);
+        let streamed = fetch_feed(&http_client(&opts).unwrap(), &opts, 1)
+            .await
+            .unwrap();
+        let buffered = parse_feed(feed.as_bytes(), false).unwrap();
+        let streamed = serde_json::to_string(&parse_releases(streamed, &opts).unwrap()).unwrap();
+        let buffered = serde_json::to_string(&parse_releases(buffered, &opts).unwrap()).unwrap();
+        assert!(streamed.len() > 1_000_000);
+        assert_eq!(streamed, buffered);
+    }
+
+    #[test]
+    fn renders_template_placeholders() {
+        let release = release();
+        assert_eq!(
+            render_template(&release, "{title} at {timestamp:%Y-%m-%d %H:%M}").unwrap(),
+            "Stable Channel Update for ChromeOS at 2024-01-02 03:04"
+        );
+        assert_eq!(
+            render_template(&release, "{timestamp}").unwrap(),
+            "2024-01-02T03:04:05+00:00"
+        );
+    }
+
+    #[test]
+    fn renders_doubled_braces_as_literal_braces() {
+        let release = release();
+        assert_eq!(render_template(&release, "{{title}}").unwrap(), "{title}");
+        assert_eq!(
+            render_template(&release, "{{ {title} }}").unwrap(),
+            "{ Stable Channel Update for ChromeOS }"
+        );
+        assert_eq!(render_template(&release, "}}{{").unwrap(), "}{");
+    }
+
+    #[test]
+    fn interprets_escapes_in_templates() {
+        let release = release();
+        assert_eq!(
+            render_template(&release, r"## {title}\n{timestamp:%Y-%m-%d}\n").unwrap(),
+            "## Stable Channel Update for ChromeOS\n2024-01-02\n"
+        );
+        assert_eq!(render_template(&release, r"a\tb").unwrap(), "a\tb");
+        assert_eq!(render_template(&release, r"a\\nb").unwrap(), r"a\nb");
+        assert_eq!(render_template(&release, r"C:\data\").unwrap(), r"C:\data\");
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        let release = release();
+        let err = render_template(&release, "{version}").unwrap_err();
+        assert!(
+            err.starts_with("unknown template field `version`"),
+            "{}",
+            err
+        );
+        assert!(render_template(&release, "{title:%Y}").is_err());
+        assert!(render_template(&release, "{timestamp:%Q}").is_err());
+    }
+
+    #[test]
+    fn rejects_unmatched_braces() {
+        let release = release();
+        let err = render_template(&release, "{title").unwrap_err();
+        assert!(err.starts_with("unclosed `{`"), "{}", err);
+        let err = render_template(&release, "title}").unwrap_err();
+        assert!(err.starts_with("unmatched `}`"), "{}", err);
+    }
+
+    #[test]
+    fn decodes_entities_left_in_the_text() {
+        let body = || Some("<p>AT&amp;amp;T&amp;#x27;s update</p>".to_string());
+        assert_eq!(render_content(body(), &cli(&[])), "AT&T's update\n");
+        assert_eq!(
+            render_content(body(), &cli(&["-D", "plain"])),
+            "AT&T's update\n"
+        );
+        assert_eq!(
+            render_content(body(), &cli(&["--no-entity-decode"])),
+            "AT&amp;T&#x27;s update\n"
+        );
+    }
+
+    #[cfg(feature = "decorator-html")]
+    #[test]
+    fn keeps_underlines_as_tags_in_html() {
+        let body = || Some("<p>An <u>underlined</u> word</p>".to_string());
+        assert_eq!(
+            render_content(body(), &cli(&["--underline-as", "html", "-D", "html"])),
+            "<p>An <u>underlined</u> word</p>"
+        );
+        assert_eq!(
+            render_content(body(), &cli(&["--underline-as", "html"])),
+            "An <u>underlined</u> word\n"
+        );
+    }
+
+    #[cfg(feature = "decorator-html")]
+    #[test]
+    fn escapes_decoded_entities_once_in_html() {
+        let body = Some("<p>AT&amp;amp;T&amp;#x27;s &lt;update&gt;</p>".to_string());
+        assert_eq!(
+            render_content(body, &cli(&["-D", "html"])),
+            "<p>AT&amp;T's &lt;update&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn flattens_definition_lists() {
+        let html = r#"<DL class="notes"><dt id="a">Term</DT><dd class="x">Definition</Dd></dl>"#;
+        assert_eq!(
+            flatten_definition_lists(html.into()),
+            "<div><p><strong>Term</strong></p><dl><dd>Definition</dd></dl></div>"
+        );
+        assert_eq!(
+            html2md(html.into(), Decorator::Markdown, false, usize::MAX),
+            "**Term**\n\n  Definition\n"
+        );
+    }
+
+    #[test]
+    fn flags_versions_announced_by_another_post() {
+        let entry = |id: &str, updated: &str, text: &str| {
+            format!(
+                r#"<entry><id>{}</id><title>Stable Channel Update for ChromeOS</title><updated>{}</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;The Stable channel is being {} 120.0.6099.235 (Platform version: 15662.76.0).&lt;/p&gt;</content></entry>"#,
+                id, updated, text
+            )
+        };
+        let mut history = VersionHistory::new();
+        let mut first = releases(&entry("a", "2024-01-02T00:00:00Z", "updated to"), &[]);
+        mark_rereleases(&mut first, &mut history);
+        assert_eq!(first[0].version(), Some("15662.76.0"));
+        assert!(!first[0].rereleased);
+        // An edit of the same post isn't a re-release.
+        let mut edited = releases(&entry("a", "2024-01-03T00:00:00Z", "updated today to"), &[]);
+        mark_rereleases(&mut edited, &mut history);
+        assert!(!edited[0].rereleased);
+        let mut again = releases(&entry("b", "2024-01-04T00:00:00Z", "updated again to"), &[]);
+        mark_rereleases(&mut again, &mut history);
+        assert!(again[0].rereleased);
+        assert_eq!(history["15662.76.0"].len(), 3);
+    }
+
+    #[test]
+    fn filters_chrome_and_platform_versions_separately() {
+        let entries = r#"<entry><id>old</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T00:00:00Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;The Stable channel is being updated to 120.0.6099.235 (Platform version: 15662.76.0).&lt;/p&gt;</content></entry>
+            <entry><id>new</id><title>Beta Channel Update for ChromeOS</title><updated>2024-01-03T00:00:00Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;The Beta channel is being updated to 121.0.6167.50 (Platform version: 15699.33.0).&lt;/p&gt;</content></entry>"#;
+        let filtered = |args: &[&str]| {
+            let mut releases = releases(entries, args);
+            filter_versions(&mut releases, &cli(args));
+            releases.into_iter().map(|x| x.id).collect::<Vec<_>>()
+        };
+        assert_eq!(filtered(&["--version-min", "121"]), ["new"]);
+        // Every platform version is above a Chrome milestone.
+        assert_eq!(filtered(&["--platform-min", "121"]), ["old", "new"]);
+        assert_eq!(filtered(&["--platform-min", "15662.76.1"]), ["new"]);
+        assert!(filtered(&["--version-min", "15662"]).is_empty());
+    }
+
+    #[test]
+    fn only_trims_the_footer_at_the_end() {
+        let body = [
+            "&lt;p&gt;The Stable channel is being updated to 120.0.6099.235.&lt;/p&gt;",
+            "&lt;p&gt;Chrome now syncs faster.&lt;/p&gt;",
+            "&lt;ul&gt;&lt;li&gt;One&lt;/li&gt;&lt;li&gt;Two&lt;/li&gt;&lt;li&gt;Three&lt;/li&gt;\
+             &lt;li&gt;Four&lt;/li&gt;&lt;li&gt;Five&lt;/li&gt;&lt;li&gt;Six&lt;/li&gt;\
+             &lt;li&gt;Seven&lt;/li&gt;&lt;/ul&gt;",
+            "&lt;p&gt;If you find new issues, please let us know:&lt;/p&gt;",
+            "&lt;ul&gt;&lt;li&gt;File a bug&lt;/li&gt;&lt;li&gt;Visit the community&lt;/li&gt;&lt;/ul&gt;",
+            "&lt;p&gt;Interested in switching channels? Find out how.&lt;/p&gt;",
+            "&lt;p&gt;Jane Doe&lt;br&gt;Google ChromeOS&lt;/p&gt;",
+        ]
+        .concat();
+        let entry = format!(
+            r#"<entry><id>e1</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T00:00:00Z</updated><category term="ChromeOS"/><content type="html">{}</content></entry>"#,
+            body
+        );
+        let release = releases(&entry, &[]).remove(0);
+        assert!(release
+            .footer
+            .unwrap()
+            .starts_with("If you find new issues"));
+        let release = releases(&entry, &["--footer-phrase", "Chrome", "--show-filtered"]).remove(0);
+        // Only the end of the signature is in the footer, the body mentioning Chrome is kept.
+        assert_eq!(release.footer.as_deref(), Some("Google ChromeOS"));
+        assert!(release.content.contains("Chrome now syncs faster."));
+        assert!(release.content.contains("Seven"));
+    }
+
+    #[test]
+    fn parses_the_channel_released_to() {
+        assert_eq!(
+            parse_channel(
+                "The Beta channel has been promoted to Stable for ChromeOS",
+                false
+            ),
+            Some(Channel::Stable)
+        );
+        assert_eq!(
+            parse_channel(
+                "The Dev channel has been updated to the Beta channel",
+                false
+            ),
+            Some(Channel::Beta)
+        );
+        assert_eq!(
+            parse_channel(
+                "The Beta channel has been updated to 121.0.6167.50 (Platform version: 15699.33.0)",
+                false
+            ),
+            Some(Channel::Beta)
+        );
+        assert_eq!(
+            parse_channel(
+                "The Stable channel is being updated to 120.0.6099.235",
+                false
+            ),
+            Some(Channel::Stable)
+        );
+        assert_eq!(
+            parse_channel("The Stable channel", true),
+            Some(Channel::Lts)
+        );
+        assert_eq!(parse_channel("A new version is rolling out", false), None);
+    }
+
+    #[test]
+    fn parses_lts_announcements() {
+        assert_eq!(
+            parse_lts(
+                "LTS-120 is being updated in the LTS channel to 120.0.6099.288 (Platform Version: \
+                 15662.115.0) for most ChromeOS devices."
+            ),
+            (true, Some(120))
+        );
+        assert_eq!(
+            parse_lts(
+                "A new LTC version 126.0.6478.222 (Platform Version: 15886.76.0) is rolling out \
+                 for most ChromeOS devices."
+            ),
+            (true, None)
+        );
+        assert_eq!(
+            parse_lts("The new LTC-126 version 126.0.6478.238 is rolling out for most devices."),
+            (true, Some(126))
+        );
+        assert_eq!(
+            parse_lts("The Stable channel is being updated to 120.0.6099.235 for most devices."),
+            (false, None)
+        );
+    }
+
+    #[test]
+    fn parses_lts_releases() {
+        let releases = releases(
+            r#"<entry><id>lts</id><title>Long Term Support Channel Update for ChromeOS</title><updated>2024-03-05T00:00:00Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;LTS-120 is being updated in the LTS channel to 120.0.6099.288 (Platform Version: 15662.115.0) for most ChromeOS devices.&lt;/p&gt;</content></entry>
+            <entry><id>ltc</id><title>Long Term Support Candidate Channel Update for ChromeOS</title><updated>2024-03-04T00:00:00Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;A new LTC version 126.0.6478.222 (Platform Version: 15886.76.0) is rolling out for most ChromeOS devices.&lt;/p&gt;</content></entry>"#,
+            &[],
+        );
+        let lts = &releases[0];
+        assert!(lts.is_lts);
+        assert_eq!(lts.channel, Some(Channel::Lts));
+        assert_eq!(lts.milestone, Some(120));
+        assert_eq!(lts.chrome_version.as_deref(), Some("120.0.6099.288"));
+        assert_eq!(lts.platform_version.as_deref(), Some("15662.115.0"));
+        let ltc = &releases[1];
+        assert!(ltc.is_lts);
+        assert_eq!(ltc.channel, Some(Channel::Lts));
+        // Taken from the version when the announcement doesn't name the milestone.
+        assert_eq!(ltc.milestone, Some(126));
+        assert_eq!(ltc.platform_version.as_deref(), Some("15886.76.0"));
+    }
+
+    #[test]
+    fn falls_back_to_the_summary_without_content() {
+        let releases = releases(
+            r#"<entry><id>summary-only</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T00:00:00Z</updated><category term="ChromeOS"/><summary type="html">&lt;p&gt;The Stable channel is being updated to 120.0.6099.235 (Platform Version: 15662.76.0) for most ChromeOS devices.&lt;/p&gt;</summary></entry>"#,
+            &[],
+        );
+        assert_eq!(releases.len(), 1);
+        assert_eq!(
+            releases[0].content,
+            "The Stable channel is being updated to 120.0.6099.235 (Platform Version: 15662.76.0) \
+             for most ChromeOS devices."
+        );
+        assert_eq!(releases[0].platform_version.as_deref(), Some("15662.76.0"));
+    }
+
+    #[test]
+    fn humanizes_durations_in_their_largest_unit() {
+        use chrono::Duration;
+        let cases = [
+            (Duration::seconds(-5), "0 seconds"),
+            (Duration::seconds(0), "0 seconds"),
+            (Duration::seconds(1), "1 second"),
+            (Duration::seconds(59), "59 seconds"),
+            (Duration::seconds(60), "1 minute"),
+            (Duration::seconds(119), "1 minute"),
+            (Duration::minutes(59), "59 minutes"),
+            (Duration::minutes(60), "1 hour"),
+            (Duration::hours(23), "23 hours"),
+            (Duration::hours(24), "1 day"),
+            (Duration::days(6), "6 days"),
+            (Duration::days(7), "1 week"),
+            (Duration::days(13), "1 week"),
+            (Duration::weeks(52), "52 weeks"),
+        ];
+        for (duration, expected) in cases {
+            assert_eq!(humanize_duration(duration), expected, "{:?}", duration);
+        }
+    }
+
+    #[test]
+    fn builds_feed_urls() {
+        assert_eq!(
+            build_feed_url(&cli(&[])),
+            "https://www.blogger.com/feeds/8982037438137564684/posts/default"
+        );
+        assert_eq!(
+            build_feed_url(&cli(&["--blog-id", "123"])),
+            "https://www.blogger.com/feeds/123/posts/default"
+        );
+        assert_eq!(
+            build_feed_url(&cli(&["--feed-url", "https://example.com/feed?alt=atom"])),
+            "https://example.com/feed?alt=atom"
+        );
+    }
+
+    #[test]
+    fn adds_paging_parameters_to_feed_urls() {
+        let opts = cli(&["-r", "10"]);
+        assert_eq!(
+            paged_feed_url(&build_feed_url(&opts), &opts, 11),
+            "https://www.blogger.com/feeds/8982037438137564684/posts/default\
+             ?start-index=11&max-results=10"
+        );
+        // Parameters already in the URL are kept.
+        assert_eq!(
+            paged_feed_url("https://example.com/feed?alt=atom", &opts, 1),
+            "https://example.com/feed?alt=atom&start-index=1&max-results=10"
+        );
+    }
+
+    #[test]
+    fn adds_published_dates_to_feed_urls_in_utc() {
+        // Offsets are converted to UTC, so there's no `+` that would be decoded as a space.
+        let opts = cli(&[
+            "--published-min",
+            "2024-01-15T03:00:00+02:00",
+            "--published-max",
+            "2024-02-01",
+        ]);
+        assert_eq!(
+            paged_feed_url("https://example.com/feed", &opts, 1),
+            "https://example.com/feed?start-index=1&max-results=25\
+             &published-min=2024-01-15T01:00:00Z&published-max=2024-02-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn accepts_output_options_after_the_diff_command() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+        let opts = cli(&[
+            "diff", "old.xml", "new.xml", "-f", "json", "-D", "plain", "--gfm",
+        ]);
+        assert!(matches!(opts.command, Some(Commands::Diff { .. })));
+        assert!(matches!(opts.format[..], [OutputFormat::Json]));
+        assert!(matches!(opts.decorator(), Decorator::Plain));
+        assert!(opts.gfm);
+    }
+
+    #[test]
+    fn fails_on_empty_diffs_only_without_releases_in_the_new_feed() {
+        let dir =
+            std::env::temp_dir().join(format!("crosreleasenotifier-diff-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let chromeos = dir.join("chromeos.xml");
+        std::fs::write(
+            &chromeos,
+            feed(r#"<entry><id>e1</id><title>Stable Channel Update for ChromeOS</title><updated>2024-01-02T00:00:00Z</updated><category term="ChromeOS"/><content type="html">&lt;p&gt;The Stable channel is being updated to 120.0.6099.235.&lt;/p&gt;</content></entry>"#),
+        )
+        .unwrap();
+        let other = dir.join("other.xml");
+        std::fs::write(&other, feed("")).unwrap();
+        let opts = cli(&["--fail-on-empty"]);
+        // Nothing new isn't an error.
+        let releases = diff_snapshots(&chromeos, &chromeos, &opts).unwrap();
+        let err = diff_snapshots(&chromeos, &other, &opts).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(releases.is_empty());
+        assert_eq!(err.to_string(), NO_RELEASES);
+    }
+
+    #[test]
+    fn hashes_the_paging_and_width_of_cached_releases() {
+        let hash = release_options_hash(&cli(&[]));
+        assert_ne!(hash, release_options_hash(&cli(&["--paginate"])));
+        assert_ne!(hash, release_options_hash(&cli(&["--width", "80"])));
+        assert_eq!(hash, release_options_hash(&cli(&["-f", "json"])));
+    }
+
+    #[test]
+    fn replaces_files_atomically() {
+        let path =
+            std::env::temp_dir().join(format!("crosreleasenotifier-seen-{}", std::process::id()));
+        write_atomic(&path, b"[\"a\"]").unwrap();
+        write_atomic(&path, b"[\"a\",\"b\"]").unwrap();
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        assert!(!Path::new(&temp).exists());
+        assert_eq!(std::fs::read(&path).unwrap(), b"[\"a\",\"b\"]");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reads_secrets_from_files() {
+        let path = std::env::temp_dir().join(format!("crosreleasenotifier-{}", std::process::id()));
+        std::fs::write(&path, "https://discord.com/api/webhooks/1/token\n").unwrap();
+        let mut opts = cli(&["--discord-webhook-file", path.to_str().unwrap()]);
+        resolve_secrets(&mut opts).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            opts.discord_webhook.as_deref(),
+            Some("https://discord.com/api/webhooks/1/token")
+        );
+    }
+
+    #[test]
+    fn parses_bug_ids_in_order() {
+        assert_eq!(
+            parse_bug_ids(
+                "Fixed issue 123456, b/7654321 and crbug.com/123456, see \
+                 issues.chromium.org/issues/345678 and crbug/1234"
+            ),
+            ["crbug/123456", "b/7654321", "crbug/345678"]
+        );
+    }
+
+    #[test]
+    fn wraps_paragraphs_at_the_width() {
+        let paragraph = "The Stable channel is being updated to 120.0.6099.235 for most devices.";
+        let wrapped = wrap_text(paragraph, 30);
+        assert_eq!(
+            wrapped,
+            "The Stable channel is being\nupdated to 120.0.6099.235 for\nmost devices."
+        );
+        assert!(wrapped.lines().all(|x| x.chars().count() <= 30));
+        assert_eq!(wrap_text(paragraph, usize::MAX), paragraph);
+    }
+
+    #[test]
+    fn wrapping_keeps_code_fences_verbatim() {
+        let text = "Run this command to boot from USB:\n```\nsudo crossystem dev_boot_usb=1 dev_boot_signed_only=0\n```";
+        assert_eq!(
+            wrap_text(text, 20),
+            "Run this command to\nboot from USB:\n```\nsudo crossystem dev_boot_usb=1 dev_boot_signed_only=0\n```"
+        );
+    }
+
+    #[test]
+    fn wrapping_keeps_links_and_list_items_whole() {
+        let line =
+            "* See [the release notes](https://example.com/notes) and https://example.com/a/b";
+        assert_eq!(
+            wrap_line(line, 20),
+            concat!(
+                "* See\n",
+                "  [the release notes](https://example.com/notes)\n",
+                "  and\n",
+                "  https://example.com/a/b"
+            )
+        );
+    }
+}
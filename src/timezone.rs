@@ -0,0 +1,42 @@
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use std::str::FromStr;
+
+/// Timezone to show release times in, given as `utc`, `local`, an IANA name such as
+/// `Europe/Berlin` or a fixed offset such as `+05:30`.
+#[derive(Debug, Clone, Copy)]
+pub enum Timezone {
+    Utc,
+    Local,
+    Named(chrono_tz::Tz),
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    /// Formats a timestamp with a strftime format after converting it to this timezone.
+    pub fn format(self, timestamp: DateTime<Utc>, format: &str) -> String {
+        match self {
+            Timezone::Utc => timestamp.format(format).to_string(),
+            Timezone::Local => timestamp.with_timezone(&Local).format(format).to_string(),
+            Timezone::Named(tz) => timestamp.with_timezone(&tz).format(format).to_string(),
+            Timezone::Fixed(offset) => timestamp.with_timezone(&offset).format(format).to_string(),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(timezone: &str) -> Result<Timezone, String> {
+        if timezone.eq_ignore_ascii_case("utc") {
+            Ok(Timezone::Utc)
+        } else if timezone.eq_ignore_ascii_case("local") {
+            Ok(Timezone::Local)
+        } else if let Ok(tz) = timezone.parse() {
+            Ok(Timezone::Named(tz))
+        } else if let Ok(offset) = timezone.parse() {
+            Ok(Timezone::Fixed(offset))
+        } else {
+            Err("expected utc, local, an IANA name such as Europe/Berlin or an offset such as +05:30".into())
+        }
+    }
+}
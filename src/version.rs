@@ -0,0 +1,121 @@
+use regex::Regex;
+use std::{cmp::Ordering, fmt, str::FromStr, sync::OnceLock};
+
+/// A dotted version such as a Chrome version `120.0.6099.234` or a platform version
+/// `15662.76.0`, compared numerically per segment. Missing segments count as 0, so `120.0.1`
+/// equals `120.0.1.0`.
+#[derive(Debug, Clone)]
+pub struct ChromeVersion(Vec<u64>);
+
+impl ChromeVersion {
+    /// Parses a version made only of numbers separated by dots.
+    pub fn parse(version: &str) -> Option<ChromeVersion> {
+        version
+            .split('.')
+            .map(|x| x.parse().ok())
+            .collect::<Option<Vec<u64>>>()
+            .map(ChromeVersion)
+    }
+
+    /// Finds the first version in some text with three or four segments, like the Chrome and
+    /// platform versions, so dates and decimals aren't taken for one.
+    pub fn find(text: &str) -> Option<ChromeVersion> {
+        static VERSION: OnceLock<Regex> = OnceLock::new();
+        let version = VERSION.get_or_init(|| Regex::new(r"\b\d+(?:\.\d+){2,3}\b").unwrap());
+        ChromeVersion::parse(version.find(text)?.as_str())
+    }
+
+    fn segment(&self, i: usize) -> u64 {
+        self.0.get(i).copied().unwrap_or(0)
+    }
+}
+
+impl FromStr for ChromeVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<ChromeVersion, String> {
+        ChromeVersion::parse(version).ok_or_else(|| "versions are numbers separated by dots".into())
+    }
+}
+
+impl fmt::Display for ChromeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let segments: Vec<String> = self.0.iter().map(u64::to_string).collect();
+        f.write_str(&segments.join("."))
+    }
+}
+
+impl Ord for ChromeVersion {
+    fn cmp(&self, other: &ChromeVersion) -> Ordering {
+        (0..self.0.len().max(other.0.len()))
+            .map(|i| self.segment(i).cmp(&other.segment(i)))
+            .find(|x| x.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for ChromeVersion {
+    fn partial_cmp(&self, other: &ChromeVersion) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ChromeVersion {
+    fn eq(&self, other: &ChromeVersion) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ChromeVersion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> ChromeVersion {
+        version.parse().unwrap()
+    }
+
+    #[test]
+    fn pads_missing_segments_with_zero() {
+        assert_eq!(version("120.0.1"), version("120.0.1.0"));
+        assert_eq!(
+            version("120.0.1").cmp(&version("120.0.1.0")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn compares_segments_numerically() {
+        assert!(version("120.0.2") < version("120.0.10"));
+        assert!(version("120.0.10") > version("120.0.2"));
+        assert!(version("99.0.6099.234") < version("100.0.1"));
+    }
+
+    #[test]
+    fn compares_versions_of_unequal_length() {
+        assert!(version("120.0.1") < version("120.0.1.2"));
+        assert!(version("120.0.1.2") > version("120.0.1"));
+        assert!(version("120.1") > version("120.0.6099.234"));
+        assert!(version("120") < version("120.0.0.1"));
+        assert!(version("121") > version("120.99.99.99"));
+    }
+
+    #[test]
+    fn parses_only_dotted_numbers() {
+        assert_eq!(version("120.0.6099.234").to_string(), "120.0.6099.234");
+        assert!(ChromeVersion::parse("120.0.x").is_none());
+        assert!(ChromeVersion::parse("").is_none());
+    }
+
+    #[test]
+    fn finds_versions_in_text() {
+        let text =
+            "The Stable channel is updating to 120.0.6099.235 (Platform version: 15662.76.0)";
+        assert_eq!(
+            ChromeVersion::find(text).unwrap().to_string(),
+            "120.0.6099.235"
+        );
+        assert!(ChromeVersion::find("Rolled out to 1.5% of devices").is_none());
+    }
+}